@@ -0,0 +1,116 @@
+// Discs falling into the board, flying back out on takebacks and popping
+// out of the bottom in PopOut.
+// Positions are in rows, the top row being 0, so drawing only has to scale
+// them by the tile height. The motion is stepped in small fixed slices of
+// the frame delta so it looks the same at any frame rate.
+
+use crate::board::Player;
+
+// rows per second squared
+const GRAVITY: f32 = 60.0;
+// share of the speed kept when bouncing off the disc below
+const BOUNCE: f32 = 0.3;
+// slower than this after a bounce and the disc comes to rest
+const REST_SPEED: f32 = 2.0;
+const STEP: f32 = 1.0 / 240.0;
+// discs enter and leave one row above the board, where the preview sits
+pub const ABOVE: f32 = -1.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Falling {
+    pub col: usize,
+    pub row: usize,
+    pub player: Player,
+    pub y: f32,
+    speed: f32,
+    // lifted out of the board instead of dropped in
+    pub out: bool,
+}
+
+impl Falling {
+    pub fn drop(col: usize, row: usize, player: Player) -> Self {
+        Falling {
+            col,
+            row,
+            player,
+            y: ABOVE,
+            speed: 0.0,
+            out: false,
+        }
+    }
+
+    pub fn lift(col: usize, row: usize, player: Player) -> Self {
+        Falling {
+            col,
+            row,
+            player,
+            y: row as f32,
+            speed: 0.0,
+            out: true,
+        }
+    }
+
+    // moves the disc on by `delta` seconds, returns true once it settled
+    pub fn update(&mut self, delta: f32) -> bool {
+        stepped(delta, |dt| self.step(dt))
+    }
+
+    fn step(&mut self, dt: f32) -> bool {
+        if self.out {
+            self.speed -= GRAVITY * dt;
+            self.y += self.speed * dt;
+            return self.y <= ABOVE;
+        }
+
+        self.speed += GRAVITY * dt;
+        self.y += self.speed * dt;
+
+        let target = self.row as f32;
+        if self.y < target {
+            return false;
+        }
+
+        self.y = target;
+        self.speed = -self.speed * BOUNCE;
+        self.speed.abs() < REST_SPEED
+    }
+}
+
+// a disc popped out of the bottom row: the discs above fall one row into its
+// place while it drops out under the board
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Popping {
+    pub col: usize,
+    pub player: Player,
+    // rows fallen so far, from 0 to 1
+    pub y: f32,
+    speed: f32,
+}
+
+impl Popping {
+    pub fn new(col: usize, player: Player) -> Self {
+        Popping {
+            col,
+            player,
+            y: 0.0,
+            speed: 0.0,
+        }
+    }
+
+    // returns true once the column came to rest
+    pub fn update(&mut self, delta: f32) -> bool {
+        stepped(delta, |dt| {
+            self.speed += GRAVITY * dt;
+            self.y = (self.y + self.speed * dt).min(1.0);
+            self.y >= 1.0
+        })
+    }
+}
+
+// runs `step` over `delta` in slices of STEP until it returns true
+fn stepped(delta: f32, mut step: impl FnMut(f32) -> bool) -> bool {
+    let steps = (delta / STEP).ceil().max(1.0);
+    let dt = delta / steps;
+
+    (0..steps as usize).any(|_| step(dt))
+}
@@ -0,0 +1,893 @@
+use std::ops::{Index, IndexMut};
+
+use notan::prelude::*;
+
+// the classic board, network games are always played on it
+pub const ROWS: usize = 6;
+pub const COLS: usize = 7;
+// the widest and tallest board, for games with more players
+pub const MAX_COLS: usize = 10;
+pub const MAX_ROWS: usize = 8;
+// discs in a row that win a classic game
+pub const CONNECT: usize = 4;
+
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+pub enum Player {
+    #[default]
+    Red,
+    Yellow,
+    // only in hot seat games with three or four players
+    Green,
+    Purple,
+}
+
+impl Player {
+    // in turn order
+    pub const ALL: [Player; 4] = [Player::Red, Player::Yellow, Player::Green, Player::Purple];
+
+    // the opponent in a two player game
+    pub fn other(self) -> Player {
+        match self {
+            Player::Red => Player::Yellow,
+            Player::Yellow => Player::Red,
+            Player::Green => Player::Purple,
+            Player::Purple => Player::Green,
+        }
+    }
+
+    // the one after in a game of `players`
+    pub fn next(self, players: usize) -> Player {
+        Player::ALL[(self.index() + 1) % players]
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Player::Red => 0,
+            Player::Yellow => 1,
+            Player::Green => 2,
+            Player::Purple => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Player::Red => "red",
+            Player::Yellow => "yellow",
+            Player::Green => "green",
+            Player::Purple => "purple",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Player> {
+        Player::ALL.into_iter().find(|player| player.name() == name)
+    }
+}
+
+// how a game ended
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    Connected(Player),
+    Full,
+    Resigned(Player),
+    AgreedDraw,
+    Forfeit(Player),
+    // the player ran out of time
+    Timeout(Player),
+    // the same position came up a third time in PopOut
+    Repetition,
+    // the player made four in a row in misère, and lost
+    Completed(Player),
+    // everyone else resigned from a game of more than two
+    Outlasted(Player),
+}
+
+impl Outcome {
+    pub fn winner(self) -> Option<Player> {
+        match self {
+            Outcome::Connected(player) | Outcome::Outlasted(player) => Some(player),
+            Outcome::Resigned(player)
+            | Outcome::Forfeit(player)
+            | Outcome::Timeout(player)
+            | Outcome::Completed(player) => Some(player.other()),
+            Outcome::Full | Outcome::AgreedDraw | Outcome::Repetition => None,
+        }
+    }
+
+    // short explanation shown under the result
+    // `name` gives the name to show for a player
+    pub fn reason(self, name: impl Fn(Player) -> String) -> Option<String> {
+        match self {
+            Outcome::Connected(_) | Outcome::Full => None,
+            Outcome::Resigned(player) => Some(format!("{} resigned", name(player))),
+            Outcome::AgreedDraw => Some("by agreement".to_string()),
+            Outcome::Forfeit(player) => Some(format!("{} forfeited", name(player))),
+            Outcome::Timeout(player) => Some(format!("{} ran out of time", name(player))),
+            Outcome::Repetition => Some("the same position came up three times".to_string()),
+            Outcome::Completed(player) => Some(format!("{} made four in a row", name(player))),
+            Outcome::Outlasted(_) => Some("everyone else resigned".to_string()),
+        }
+    }
+}
+
+// a request waiting for the other player's answer
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Offer {
+    Draw(Player),
+    Takeback(Player),
+}
+
+impl Offer {
+    pub fn by(self) -> Player {
+        match self {
+            Offer::Draw(player) | Offer::Takeback(player) => player,
+        }
+    }
+}
+
+pub fn coin_flip(rng: &mut Random) -> Player {
+    if rng.gen_bool(0.5) {
+        Player::Yellow
+    } else {
+        Player::Red
+    }
+}
+
+// any one of the first `players`
+pub fn draw_lots(rng: &mut Random, players: usize) -> Player {
+    Player::ALL[rng.gen_range(0..players)]
+}
+
+// rule set of a game, both sides of a network game must agree on it
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+pub enum Variant {
+    #[default]
+    Classic,
+    // a turn can also take one of your own discs out of the bottom row
+    PopOut,
+    // each player has one of every `Power` to play instead of a disc
+    PowerUp,
+    // the left and right edges meet, lines can run across them
+    Cylinder,
+    // whoever makes four in a row loses
+    Misere,
+    // gravity is off, discs go into any empty cell
+    Free,
+    // the players only see their own discs until the game is over
+    Fog,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 7] = [
+        Variant::Classic,
+        Variant::PopOut,
+        Variant::PowerUp,
+        Variant::Cylinder,
+        Variant::Misere,
+        Variant::Free,
+        Variant::Fog,
+    ];
+
+    // the next one in `ALL`, for picking in menus
+    pub fn next(self) -> Variant {
+        let i = Variant::ALL
+            .iter()
+            .position(|&variant| variant == self)
+            .unwrap_or(0);
+        Variant::ALL[(i + 1) % Variant::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Classic => "classic",
+            Variant::PopOut => "popout",
+            Variant::PowerUp => "powerup",
+            Variant::Cylinder => "cylinder",
+            Variant::Misere => "misere",
+            Variant::Free => "free",
+            Variant::Fog => "fog",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name {
+            "classic" => Some(Variant::Classic),
+            "popout" => Some(Variant::PopOut),
+            "powerup" => Some(Variant::PowerUp),
+            "cylinder" => Some(Variant::Cylinder),
+            "misere" => Some(Variant::Misere),
+            "free" => Some(Variant::Free),
+            "fog" => Some(Variant::Fog),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Cell {
+    Disc(Player),
+    // a wall from the power-ups, it belongs to nobody and blocks lines
+    Wall,
+}
+
+impl Cell {
+    pub fn player(self) -> Option<Player> {
+        match self {
+            Cell::Disc(player) => Some(player),
+            Cell::Wall => None,
+        }
+    }
+}
+
+// the cells row by row from the top, `table[row][col]`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Table {
+    cols: usize,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Table {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Table {
+            cols,
+            cells: vec![None; cols * rows],
+        }
+    }
+
+    // wider and taller with every player after the second
+    pub fn for_players(players: usize) -> Self {
+        match players {
+            3 => Table::new(9, 7),
+            4 => Table::new(MAX_COLS, MAX_ROWS),
+            _ => Table::default(),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.len() / self.cols
+    }
+
+    // the cell, none if it is off the board
+    pub fn get(&self, row: usize, col: usize) -> Option<Option<Cell>> {
+        if row >= self.rows() || col >= self.cols {
+            return None;
+        }
+
+        Some(self[row][col])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[Option<Cell>]> {
+        self.cells.chunks(self.cols)
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table::new(COLS, ROWS)
+    }
+}
+
+impl Index<usize> for Table {
+    type Output = [Option<Cell>];
+
+    fn index(&self, row: usize) -> &Self::Output {
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+impl IndexMut<usize> for Table {
+    fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        &mut self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+// the special discs of the PowerUp variant, one of each per player
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Power {
+    // clears the column and lands at the bottom
+    Anvil,
+    // goes off where it lands and blows up another player's disc next to it
+    Bomb,
+    Wall,
+    // a normal disc, then the same player moves again
+    Double,
+}
+
+impl Power {
+    pub const ALL: [Power; 4] = [Power::Anvil, Power::Bomb, Power::Wall, Power::Double];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Power::Anvil => "anvil",
+            Power::Bomb => "bomb",
+            Power::Wall => "wall",
+            Power::Double => "x2",
+        }
+    }
+
+    // marks the move in records and messages
+    fn letter(self) -> char {
+        match self {
+            Power::Anvil => 'a',
+            Power::Bomb => 'b',
+            Power::Wall => 'w',
+            Power::Double => 'x',
+        }
+    }
+}
+
+// what a player does on their turn
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Move {
+    Drop(usize),
+    // PopOut only, takes the player's own disc out of the bottom of the column
+    Pop(usize),
+    // PowerUp only
+    Power(Power, usize),
+    // with the swap rule, the second player takes over the first disc
+    Swap,
+    // with gravity off, a disc put straight into the cell in column and row
+    Place(usize, usize),
+}
+
+impl Move {
+    // every kind of move into the column of a board `rows` high
+    pub fn all(col: usize, rows: usize) -> impl Iterator<Item = Move> {
+        let powers = Power::ALL
+            .into_iter()
+            .map(move |power| Move::Power(power, col));
+        let places = (0..rows).map(move |row| Move::Place(col, row));
+        [Move::Drop(col), Move::Pop(col)]
+            .into_iter()
+            .chain(powers)
+            .chain(places)
+    }
+
+    // columns from 0 as in the protocol, pops marked with a `p` and power-ups
+    // with their letter, e.g. `p3` or `a3`, a swap is `s` and a placed disc
+    // is column and row like `3:5`
+    pub fn name(self) -> String {
+        match self {
+            Move::Place(col, row) => format!("{}:{}", col, row),
+            Move::Drop(col) => col.to_string(),
+            Move::Pop(col) => format!("p{}", col),
+            Move::Power(power, col) => format!("{}{}", power.letter(), col),
+            Move::Swap => "s".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Move> {
+        if name == "s" {
+            return Some(Move::Swap);
+        }
+        if let Some((col, row)) = name.split_once(':') {
+            return Some(Move::Place(col.parse().ok()?, row.parse().ok()?));
+        }
+        if let Some(col) = name.strip_prefix('p') {
+            return col.parse().ok().map(Move::Pop);
+        }
+
+        let power = Power::ALL
+            .into_iter()
+            .find(|p| name.starts_with(p.letter()));
+        match power {
+            Some(power) => name[1..].parse().ok().map(|col| Move::Power(power, col)),
+            None => name.parse().ok().map(Move::Drop),
+        }
+    }
+
+    // the player moves again after it
+    pub fn extra_turn(self) -> bool {
+        matches!(self, Move::Power(Power::Double, _))
+    }
+}
+
+// plays the move for the player, false if it isn't possible
+pub fn apply_move(table: &mut Table, mv: Move, player: Player) -> bool {
+    match mv {
+        Move::Drop(col) => drop_piece(table, col, player).is_some(),
+        Move::Pop(col) => can_pop(table, col, player) && pop_piece(table, col).is_some(),
+        Move::Power(power, col) => play_power(table, power, col, player).is_some(),
+        Move::Place(col, row) => place_piece(table, col, row, player),
+        Move::Swap => swap_disc(table, player),
+    }
+}
+
+// the only disc on the board becomes the player's, false unless there is
+// exactly one and it is someone else's
+pub fn swap_disc(table: &mut Table, player: Player) -> bool {
+    let mut cells = table.cells.iter_mut().flatten();
+    match (cells.next(), cells.next()) {
+        (Some(cell @ Cell::Disc(_)), None) if *cell != Cell::Disc(player) => {
+            *cell = Cell::Disc(player);
+            true
+        }
+        _ => false,
+    }
+}
+
+// drops a piece in the lowest empty row of the column and returns that row
+pub fn drop_piece(table: &mut Table, col: usize, player: Player) -> Option<usize> {
+    let row = landing_row(table, col)?;
+    table[row][col] = Some(Cell::Disc(player));
+    Some(row)
+}
+
+// plays the power-up into the column and returns the row it ends up in
+pub fn play_power(table: &mut Table, power: Power, col: usize, player: Player) -> Option<usize> {
+    if col >= table.cols() {
+        return None;
+    }
+
+    match power {
+        Power::Anvil => {
+            for row in 0..table.rows() {
+                table[row][col] = None;
+            }
+            drop_piece(table, col, player)
+        }
+        Power::Bomb => {
+            let row = landing_row(table, col)?;
+            let (target_row, target_col) = bomb_target(table, col, row, player)?;
+            // the discs above the blown up one fall into the gap
+            for above in (1..=target_row).rev() {
+                table[above][target_col] = table[above - 1][target_col];
+            }
+            table[0][target_col] = None;
+            Some(row)
+        }
+        Power::Wall => {
+            let row = landing_row(table, col)?;
+            table[row][col] = Some(Cell::Wall);
+            Some(row)
+        }
+        Power::Double => drop_piece(table, col, player),
+    }
+}
+
+// the disc a bomb landing in the cell blows up: another player's disc below
+// it, then beside it, then diagonally below and above. The bomb itself is
+// spent and leaves nothing behind.
+fn bomb_target(table: &Table, col: usize, row: usize, player: Player) -> Option<(usize, usize)> {
+    let around = [(1, 0), (0, -1), (0, 1), (1, -1), (1, 1), (-1, -1), (-1, 1)];
+    around.into_iter().find_map(|(dr, dc)| {
+        let r = row.checked_add_signed(dr).filter(|&r| r < table.rows())?;
+        let c = col.checked_add_signed(dc).filter(|&c| c < table.cols())?;
+        let other = table[r][c]?.player()?;
+        (other != player).then_some((r, c))
+    })
+}
+
+// the row a piece dropped in the column would land in, none if it is full
+pub fn landing_row(table: &Table, col: usize) -> Option<usize> {
+    if col >= table.cols() {
+        return None;
+    }
+
+    (0..table.rows())
+        .rev()
+        .find(|&row| table[row][col].is_none())
+}
+
+// removes the top piece of the column and returns its row, used to take
+// moves back
+pub fn lift_piece(table: &mut Table, col: usize) -> Option<(usize, Player)> {
+    if col >= table.cols() {
+        return None;
+    }
+
+    let row = (0..table.rows()).find(|&row| table[row][col].is_some())?;
+    Some((row, table[row][col].take()?.player()?))
+}
+
+// whether the bottom disc of the column is the player's
+pub fn can_pop(table: &Table, col: usize, player: Player) -> bool {
+    col < table.cols() && table[table.rows() - 1][col] == Some(Cell::Disc(player))
+}
+
+// takes out the bottom disc of the column, the ones above fall one row
+pub fn pop_piece(table: &mut Table, col: usize) -> Option<Player> {
+    let bottom = table.rows() - 1;
+    let player = table[bottom].get(col).copied()??.player()?;
+    for row in (1..=bottom).rev() {
+        table[row][col] = table[row - 1][col];
+    }
+    table[0][col] = None;
+
+    Some(player)
+}
+
+// puts a popped disc back under the column, to take a pop back
+pub fn unpop_piece(table: &mut Table, col: usize, player: Player) {
+    if col >= table.cols() {
+        return;
+    }
+
+    let bottom = table.rows() - 1;
+    for row in 0..bottom {
+        table[row][col] = table[row + 1][col];
+    }
+    table[bottom][col] = Some(Cell::Disc(player));
+}
+
+// puts a disc in the cell, wherever it is, false if the cell is taken
+pub fn place_piece(table: &mut Table, col: usize, row: usize, player: Player) -> bool {
+    if col >= table.cols() || row >= table.rows() || table[row][col].is_some() {
+        return false;
+    }
+
+    table[row][col] = Some(Cell::Disc(player));
+    true
+}
+
+pub fn is_full(table: &Table) -> bool {
+    // go through the top row and find if any spot is open
+    table[0].iter().all(|cell| cell.is_some())
+}
+
+pub fn check_winner(table: &Table) -> Option<Player> {
+    [Player::Red, Player::Yellow]
+        .into_iter()
+        .find(|&player| connected(table, player, CONNECT, false, false))
+}
+
+// whether the player has `length` in a row anywhere, in PopOut several can
+// at once. With `exact` longer lines don't count.
+// With `wrap` lines run on from the last column into the first; every line is
+// only followed rightwards from its first disc, and being shorter than the
+// board it never comes round to a column twice.
+pub fn connected(table: &Table, player: Player, length: usize, wrap: bool, exact: bool) -> bool {
+    // horizontal, vertical, ascending and descending diagonal
+    const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (-1, 1), (1, 1)];
+
+    let (rows, cols) = (table.rows() as isize, table.cols() as isize);
+    let owned = |r: isize, c: isize| {
+        let c = if wrap { c.rem_euclid(cols) } else { c };
+        (0..rows).contains(&r)
+            && (0..cols).contains(&c)
+            && table[r as usize][c as usize] == Some(Cell::Disc(player))
+    };
+
+    for row in 0..rows {
+        for col in 0..cols {
+            for (dr, dc) in DIRECTIONS {
+                // an exact line is measured from its first disc
+                if exact && owned(row - dr, col - dc) {
+                    continue;
+                }
+
+                let run = (0..=length as isize)
+                    .take_while(|&step| owned(row + dr * step, col + dc * step))
+                    .count();
+                if run == length || (!exact && run > length) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // one line per row from the top, `r`/`y`/`g`/`p` for discs, `#` for a wall
+    pub(crate) fn table(text: &str) -> Table {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let mut table = Table::new(rows[0].len(), rows.len());
+        for (row, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                table[row][col] = match c {
+                    'r' => Some(Cell::Disc(Player::Red)),
+                    'y' => Some(Cell::Disc(Player::Yellow)),
+                    'g' => Some(Cell::Disc(Player::Green)),
+                    'p' => Some(Cell::Disc(Player::Purple)),
+                    '#' => Some(Cell::Wall),
+                    _ => None,
+                };
+            }
+        }
+        table
+    }
+
+    #[test]
+    fn bomb_blows_up_the_disc_below() {
+        let mut board = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            ...y...
+            ...r...
+            ",
+        );
+        let row = play_power(&mut board, Power::Bomb, 3, Player::Red);
+        assert_eq!(row, Some(3));
+        let after = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            .......
+            ...r...
+            ",
+        );
+        assert_eq!(board, after);
+    }
+
+    #[test]
+    fn bomb_lets_the_column_beside_settle() {
+        let mut board = table(
+            "
+            .......
+            .......
+            .......
+            ..r....
+            ..y....
+            ..#r...
+            ",
+        );
+        // lands on red, so the yellow beside it goes and red falls into its place
+        assert_eq!(play_power(&mut board, Power::Bomb, 3, Player::Red), Some(4));
+        let after = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            ..r....
+            ..#r...
+            ",
+        );
+        assert_eq!(board, after);
+    }
+
+    #[test]
+    fn bomb_needs_an_opponent_next_to_it() {
+        let before = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            ..#....
+            y.#r...
+            ",
+        );
+        let mut board = before.clone();
+        assert_eq!(play_power(&mut board, Power::Bomb, 3, Player::Red), None);
+        assert_eq!(board, before);
+        // and walls belong to nobody
+        assert_eq!(play_power(&mut board, Power::Bomb, 1, Player::Yellow), None);
+        assert!(play_power(&mut board, Power::Bomb, 1, Player::Red).is_some());
+        assert_eq!(board[5][0], None);
+    }
+
+    #[test]
+    fn lines_in_every_direction() {
+        let across = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            .......
+            .rrrr..
+            ",
+        );
+        let down = table(
+            "
+            .......
+            .......
+            y......
+            y......
+            y......
+            y......
+            ",
+        );
+        let rising = table(
+            "
+            .......
+            .......
+            ......r
+            .....r.
+            ....r..
+            ...r...
+            ",
+        );
+        let falling = table(
+            "
+            r......
+            .r.....
+            ..r....
+            ...r...
+            .......
+            .......
+            ",
+        );
+        assert!(connected(&across, Player::Red, 4, false, false));
+        assert!(connected(&down, Player::Yellow, 4, false, false));
+        assert!(connected(&rising, Player::Red, 4, false, false));
+        assert!(connected(&falling, Player::Red, 4, false, false));
+        assert!(!connected(&across, Player::Yellow, 4, false, false));
+        assert_eq!(check_winner(&down), Some(Player::Yellow));
+    }
+
+    #[test]
+    fn walls_and_gaps_break_lines() {
+        let board = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            rr.rr..
+            rr#rr..
+            ",
+        );
+        assert!(!connected(&board, Player::Red, 4, false, false));
+        assert!(connected(&board, Player::Red, 2, false, false));
+        assert_eq!(check_winner(&board), None);
+    }
+
+    #[test]
+    fn popping_lets_the_column_fall() {
+        let mut board = table(
+            "
+            .......
+            .......
+            .......
+            y......
+            r......
+            y......
+            ",
+        );
+        assert!(!can_pop(&board, 0, Player::Red));
+        assert!(can_pop(&board, 0, Player::Yellow));
+        assert_eq!(pop_piece(&mut board, 0), Some(Player::Yellow));
+        let popped = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            y......
+            r......
+            ",
+        );
+        assert_eq!(board, popped);
+        unpop_piece(&mut board, 0, Player::Yellow);
+        assert_eq!(board[5][0], Some(Cell::Disc(Player::Yellow)));
+        assert_eq!(board[3][0], Some(Cell::Disc(Player::Yellow)));
+    }
+
+    #[test]
+    fn lines_wrap_across_the_seam() {
+        let across = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            .......
+            rr...rr
+            ",
+        );
+        let diagonal = table(
+            "
+            .......
+            .......
+            y......
+            ......y
+            .....yr
+            ....yrr
+            ",
+        );
+        assert!(connected(&across, Player::Red, 4, true, false));
+        assert!(!connected(&across, Player::Red, 4, false, false));
+        assert!(connected(&diagonal, Player::Yellow, 4, true, false));
+        assert!(!connected(&diagonal, Player::Yellow, 4, false, false));
+    }
+
+    #[test]
+    fn wrapped_lines_stop_at_the_top_and_bottom() {
+        let board = table(
+            "
+            r......
+            .......
+            .......
+            .......
+            .....r.
+            ......r
+            ",
+        );
+        // the diagonal would go on in the top row if the board wrapped that way
+        assert!(!connected(&board, Player::Red, 3, true, false));
+        assert!(connected(&board, Player::Red, 2, true, false));
+    }
+
+    #[test]
+    fn only_a_lone_opponent_disc_can_be_swapped() {
+        let mut board = Table::default();
+        assert!(!swap_disc(&mut board, Player::Yellow));
+        drop_piece(&mut board, 3, Player::Red);
+        assert!(!swap_disc(&mut board, Player::Red));
+        assert!(swap_disc(&mut board, Player::Yellow));
+        assert_eq!(board[5][3], Some(Cell::Disc(Player::Yellow)));
+        drop_piece(&mut board, 3, Player::Red);
+        assert!(!swap_disc(&mut board, Player::Red));
+    }
+
+    #[test]
+    fn exact_lines_reject_overlines() {
+        let five = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            .......
+            .rrrrr.
+            ",
+        );
+        assert!(connected(&five, Player::Red, 4, false, false));
+        assert!(!connected(&five, Player::Red, 4, false, true));
+        assert!(connected(&five, Player::Red, 5, false, true));
+        assert!(!connected(&five, Player::Red, 6, false, true));
+
+        // an overline doesn't spoil an exact line elsewhere
+        let both = table(
+            "
+            .......
+            .......
+            r......
+            r......
+            r......
+            rrrrrr.
+            ",
+        );
+        assert!(connected(&both, Player::Red, 4, false, true));
+        assert!(!connected(&both, Player::Red, 5, false, true));
+    }
+
+    #[test]
+    fn exact_lines_on_free_boards() {
+        let mut board = Table::new(8, 8);
+        for i in 0..3 {
+            assert!(place_piece(&mut board, i, i, Player::Yellow));
+        }
+        assert!(!place_piece(&mut board, 1, 1, Player::Red));
+        assert!(!place_piece(&mut board, 8, 0, Player::Red));
+        assert!(connected(&board, Player::Yellow, 3, false, true));
+        assert!(place_piece(&mut board, 3, 3, Player::Yellow));
+        assert!(!connected(&board, Player::Yellow, 3, false, true));
+        assert!(connected(&board, Player::Yellow, 4, false, true));
+    }
+
+    #[test]
+    fn a_ring_round_the_cylinder_is_no_exact_line() {
+        let ring = table(
+            "
+            .......
+            .......
+            .......
+            .......
+            .......
+            rrrrrrr
+            ",
+        );
+        assert!(connected(&ring, Player::Red, 4, true, false));
+        assert!(!connected(&ring, Player::Red, 4, true, true));
+    }
+}
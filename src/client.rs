@@ -0,0 +1,77 @@
+// Non blocking line connection, polled once per frame. Used to talk to a
+// game server as well as to the other side of a LAN game.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::protocol::Message;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    pub fn open(addr: &str) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "unknown address"))?;
+
+        Connection::from_stream(TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+
+        Ok(Connection {
+            stream,
+            buffer: vec![],
+            closed: false,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn send<M: Message>(&mut self, msg: &M) {
+        if self.closed {
+            return;
+        }
+
+        if writeln!(self.stream, "{}", msg.encode()).is_err() {
+            self.closed = true;
+        }
+    }
+
+    // returns every complete message received since the last call
+    pub fn poll<M: Message>(&mut self) -> Vec<M> {
+        let mut chunk = [0; 1024];
+        while !self.closed {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+
+        let mut messages = vec![];
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(msg) = M::decode(line.trim()) {
+                messages.push(msg);
+            }
+        }
+
+        messages
+    }
+}
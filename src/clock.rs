@@ -0,0 +1,136 @@
+// Chess clocks. Each player has a bank of time that runs down on their turn,
+// the bonus either adds an increment after every move or gives a delay that
+// is used up before the bank starts running.
+// Time controls are written like in chess: `3+2` is three minutes plus two
+// seconds a move, `5d3` is five minutes with a three second delay.
+
+use crate::board::Player;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Bonus {
+    Increment(u32),
+    Delay(u32),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TimeControl {
+    pub minutes: u32,
+    pub bonus: Bonus,
+}
+
+// offered when picking a clock before a game
+pub const PRESETS: [TimeControl; 6] = [
+    TimeControl::new(1, Bonus::Increment(0)),
+    TimeControl::new(3, Bonus::Increment(0)),
+    TimeControl::new(3, Bonus::Increment(2)),
+    TimeControl::new(5, Bonus::Increment(0)),
+    TimeControl::new(5, Bonus::Delay(3)),
+    TimeControl::new(10, Bonus::Increment(5)),
+];
+
+impl TimeControl {
+    pub const fn new(minutes: u32, bonus: Bonus) -> Self {
+        TimeControl { minutes, bonus }
+    }
+
+    pub fn name(self) -> String {
+        match self.bonus {
+            Bonus::Increment(secs) => format!("{}+{}", self.minutes, secs),
+            Bonus::Delay(secs) => format!("{}d{}", self.minutes, secs),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TimeControl> {
+        let (minutes, bonus) = if let Some((minutes, secs)) = name.split_once('+') {
+            (minutes, Bonus::Increment(secs.parse().ok()?))
+        } else {
+            let (minutes, secs) = name.split_once('d')?;
+            (minutes, Bonus::Delay(secs.parse().ok()?))
+        };
+
+        let minutes = minutes.parse().ok().filter(|&minutes| minutes > 0)?;
+        Some(TimeControl::new(minutes, bonus))
+    }
+
+    // the next preset, going back to no clock after the last one
+    pub fn cycle(control: Option<TimeControl>) -> Option<TimeControl> {
+        let Some(control) = control else {
+            return Some(PRESETS[0]);
+        };
+
+        match PRESETS.iter().position(|&preset| preset == control) {
+            Some(i) => PRESETS.get(i + 1).copied(),
+            None => Some(PRESETS[0]),
+        }
+    }
+}
+
+// "none" stands for untimed games on the wire and in records
+pub fn control_name(control: Option<TimeControl>) -> String {
+    control.map_or("none".to_string(), |control| control.name())
+}
+
+// below this the clock turns red and a warning sounds
+pub const LOW_TIME: f32 = 10.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Clock {
+    pub control: TimeControl,
+    // by `Player::index`
+    left: [f32; 4],
+    delay: f32,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        let base = control.minutes as f32 * 60.0;
+        Clock {
+            control,
+            left: [base; 4],
+            delay: delay_of(control),
+        }
+    }
+
+    pub fn left(&self, player: Player) -> f32 {
+        self.left[player.index()]
+    }
+
+    pub fn set_left(&mut self, player: Player, secs: f32) {
+        self.left[player.index()] = secs.max(0.0);
+    }
+
+    // runs the clock of the player on turn, returns true once they are out of time
+    pub fn tick(&mut self, player: Player, delta: f32) -> bool {
+        let spent = (delta - self.delay).max(0.0);
+        self.delay = (self.delay - delta).max(0.0);
+
+        let left = &mut self.left[player.index()];
+        *left = (*left - spent).max(0.0);
+        *left == 0.0
+    }
+
+    // called once the player finished their move
+    pub fn moved(&mut self, player: Player) {
+        if let Bonus::Increment(secs) = self.control.bonus {
+            self.left[player.index()] += secs as f32;
+        }
+        self.delay = delay_of(self.control);
+    }
+}
+
+fn delay_of(control: TimeControl) -> f32 {
+    match control.bonus {
+        Bonus::Delay(secs) => secs as f32,
+        Bonus::Increment(_) => 0.0,
+    }
+}
+
+// minutes and seconds, tenths once it gets tight
+pub fn format_time(secs: f32) -> String {
+    if secs < 10.0 {
+        format!("0:{:04.1}", (secs * 10.0).floor() / 10.0)
+    } else {
+        let secs = secs.ceil() as u32;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,100 @@
+// Turning the board of the cylinder variant. Its left and right edges meet,
+// so the view turns a column at a time to bring lines across the edge into
+// sight: Q and E, dragging sideways with the right mouse button held on the
+// board, or dragging a finger along the strip above the board. The turn only
+// changes the view, `Layout` maps columns to the screen and back.
+
+use notan::prelude::*;
+
+use crate::board::Variant;
+use crate::input::Command;
+use crate::layout::Layout;
+use crate::State;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Drag {
+    // the finger doing it, none for the mouse
+    finger: Option<u64>,
+    x: f32,
+    // the turn when the drag started
+    turn: usize,
+}
+
+#[derive(Default)]
+pub struct Spin {
+    drag: Option<Drag>,
+}
+
+impl Spin {
+    // fingers come in as events, see `Input::event`
+    pub fn event(&mut self, event: &Event, layout: &mut Layout) {
+        match *event {
+            Event::TouchStart { id, x, y } if self.drag.is_none() && in_strip(layout, x, y) => {
+                self.drag = Some(Drag {
+                    finger: Some(id),
+                    x,
+                    turn: layout.turn,
+                });
+            }
+            Event::TouchMove { id, x, .. } => {
+                if let Some(drag) = self.drag.filter(|drag| drag.finger == Some(id)) {
+                    layout.turn = turned(layout, drag, x);
+                }
+            }
+            Event::TouchEnd { id, .. } | Event::TouchCancel { id, .. }
+                if self.drag.is_some_and(|drag| drag.finger == Some(id)) =>
+            {
+                self.drag = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+// the area over the board where a finger turns it
+fn in_strip(layout: &Layout, x: f32, y: f32) -> bool {
+    let (pos, size) = layout.board();
+    (pos.x..pos.x + size.x).contains(&x) && (0.0..pos.y).contains(&y)
+}
+
+// the turn after dragging from where the drag started to `x`
+fn turned(layout: &Layout, drag: Drag, x: f32) -> usize {
+    // dragging right brings the columns on the left into sight
+    let moved = ((x - drag.x) / layout.tile().x).round() as isize;
+    (drag.turn as isize - moved).rem_euclid(layout.cols as isize) as usize
+}
+
+pub fn update(app: &App, state: &mut State) {
+    let spin = &mut state.spin;
+    let layout = &mut state.layout;
+    if state.game.variant != Variant::Cylinder {
+        layout.turn = 0;
+        spin.drag = None;
+        return;
+    }
+
+    if state.input.take(Command::TurnLeft) {
+        layout.turn = (layout.turn + 1) % layout.cols;
+    }
+    if state.input.take(Command::TurnRight) {
+        layout.turn = (layout.turn + layout.cols - 1) % layout.cols;
+    }
+
+    let (mx, my) = app.mouse.position();
+    if app.mouse.was_pressed(MouseButton::Right) && layout.column_at(mx, my).is_some() {
+        spin.drag = Some(Drag {
+            finger: None,
+            x: mx,
+            turn: layout.turn,
+        });
+    }
+
+    let Some(drag) = spin.drag.filter(|drag| drag.finger.is_none()) else {
+        return;
+    };
+    if app.mouse.is_down(MouseButton::Right) {
+        layout.turn = turned(layout, drag, mx);
+    } else {
+        spin.drag = None;
+    }
+}
@@ -0,0 +1,90 @@
+// The fog of war variant. Everyone sees their own discs, but only blank ones
+// for the others, so the column heights show and not whose disc is where;
+// the move list keeps the others' columns to itself and can't be looked back
+// through. It all comes out once the game is over. In hot seat games a
+// curtain covers the board between turns until the next player has the
+// device, in LAN games each side sees its own view. There is no computer
+// player yet, so only people are kept from the hidden discs.
+
+use notan::draw::*;
+use notan::prelude::*;
+
+use crate::board::{Player, Variant};
+use crate::input::Command;
+use crate::panel::Controls;
+use crate::{controls, State};
+
+// who has the device in a hot seat game, the board shows what they may see
+#[derive(Default)]
+pub struct Curtain {
+    ready: Option<Player>,
+}
+
+impl Curtain {
+    // a new game starts with the curtain down
+    pub fn reset(&mut self) {
+        self.ready = None;
+    }
+}
+
+// the game is still hiding discs
+pub fn is_active(state: &State) -> bool {
+    state.game.variant == Variant::Fog && !state.game.game_over()
+}
+
+// the discs of the player are blanked out on this device
+pub fn hides(state: &State, player: Player) -> bool {
+    if !is_active(state) {
+        return false;
+    }
+
+    match controls(state) {
+        // nothing shows through the curtain, the move list included
+        Controls::HotSeat => is_down(state) || state.curtain.ready != Some(player),
+        Controls::Player(me) => me != player,
+        Controls::Watching => false,
+    }
+}
+
+// the board is covered until the player on turn takes the device, a disc
+// still falling lands in sight of whoever played it
+pub fn is_down(state: &State) -> bool {
+    let game = &state.game;
+    is_active(state)
+        && controls(state) == Controls::HotSeat
+        && state.curtain.ready != Some(game.turn)
+        && !game.is_animating()
+}
+
+// a click, tap or ENTER lifts the curtain for the player on turn
+pub fn update(app: &App, state: &mut State) {
+    let clicked = state.input.clicked(app).is_some();
+    let select = state.input.take(Command::Select);
+    let drop = state.input.take(Command::Drop);
+    if clicked || select || drop {
+        state.input.take_tap();
+        state.curtain.ready = Some(state.game.turn);
+    }
+}
+
+pub fn draw(draw: &mut Draw, state: &State) {
+    let layout = &state.layout;
+    let (pos, size) = layout.screen();
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .color(state.settings.theme.background);
+
+    let name = state.player_name(state.game.turn);
+    let lines = [
+        (0.45, format!("Pass the device to {}", name), 32.0),
+        (0.55, "Tap or press ENTER when ready".to_string(), 20.0),
+    ];
+    for (at, text, size) in lines {
+        let pos = layout.banner(at);
+        draw.text(&state.font, &text)
+            .position(pos.x, pos.y)
+            .size(layout.len(size))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+}
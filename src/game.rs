@@ -0,0 +1,656 @@
+// One game on the board: the discs, whose turn it is, the clock and how it
+// ended. Scenes, input and network sessions live in `State` around it, so a
+// game can be paused, left and resumed without touching any of this.
+
+use crate::animation::{Falling, Popping};
+use crate::board::{
+    apply_move, connected, drop_piece, lift_piece, place_piece, play_power, pop_piece, swap_disc,
+    unpop_piece, Move, Offer, Outcome, Player, Power, Table, Variant, CONNECT,
+};
+use crate::clock::{Clock, TimeControl};
+
+// what two sides of a network game have to agree on before they start
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rules {
+    pub variant: Variant,
+    pub players: usize,
+    pub connect: usize,
+    pub exact: bool,
+    // columns and rows
+    pub board: (usize, usize),
+}
+
+impl Rules {
+    // for messages like "host plays popout, connect 4 on 7x6"
+    pub fn describe(&self) -> String {
+        let exactly = if self.exact { "exactly " } else { "" };
+        format!(
+            "{} for {}, connect {}{} on {}x{}",
+            self.variant.name(),
+            self.players,
+            exactly,
+            self.connect,
+            self.board.0,
+            self.board.1
+        )
+    }
+}
+
+pub struct Game {
+    pub first: Player,
+    // the seed the opener was drawn with, none if the other side picked it
+    pub seed: Option<u64>,
+    pub turn: Player,
+    pub table: Table,
+    pub moves: Vec<Move>,
+    pub outcome: Option<Outcome>,
+    // disc still moving, the win check waits for it to land
+    pub falling: Option<Falling>,
+    pub popping: Option<Popping>,
+    // rows the last disc fell before it came to rest, for the sound of it
+    pub landed: Option<usize>,
+    // picked before a game, the clock itself is reset on every start
+    pub time_control: Option<TimeControl>,
+    pub clock: Option<Clock>,
+    pub offer: Option<Offer>,
+    pub variant: Variant,
+    // two, or up to four in hot seat games on a bigger board
+    pub players: usize,
+    // discs in a row that make a line, with `exact` longer ones don't count
+    pub connect: usize,
+    pub exact: bool,
+    // players who resigned from a game of more than two, with the ply they
+    // left at; the others play on without them
+    pub out: Vec<(usize, Player)>,
+    // the swap rule is on for this game
+    pub swap: bool,
+    // the second player still has to choose whether to take over the first
+    // disc, nothing else happens until they do
+    pub swap_offered: bool,
+}
+
+impl Game {
+    pub fn new(variant: Variant, time_control: Option<TimeControl>, first: Player) -> Self {
+        Game {
+            first,
+            seed: None,
+            turn: first,
+            table: Default::default(),
+            moves: vec![],
+            outcome: None,
+            falling: None,
+            popping: None,
+            landed: None,
+            time_control,
+            clock: time_control.map(Clock::new),
+            offer: None,
+            variant,
+            players: 2,
+            connect: CONNECT,
+            exact: false,
+            out: vec![],
+            swap: false,
+            swap_offered: false,
+        }
+    }
+
+    // a new game with the same rules and board size; games of more than two
+    // players are never timed, the time control is kept for the next game of two
+    pub fn start(&mut self, first: Player) {
+        let mut game = Game::new(self.variant, self.time_control, first);
+        game.table = Table::new(self.table.cols(), self.table.rows());
+        game.players = self.players;
+        game.connect = self.connect;
+        game.exact = self.exact;
+        game.swap = self.swap;
+        if game.players > 2 {
+            game.clock = None;
+        }
+        *self = game;
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            variant: self.variant,
+            players: self.players,
+            connect: self.connect,
+            exact: self.exact,
+            board: (self.table.cols(), self.table.rows()),
+        }
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    // moves were played and nobody won yet, so there is a game to go back to
+    pub fn in_progress(&self) -> bool {
+        !self.moves.is_empty() && !self.game_over()
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.falling.is_some() || self.popping.is_some()
+    }
+
+    // who played the move at `index`, the players take turns from the first
+    // except after an x2, skipping the ones who are out
+    pub fn player_of(&self, index: usize) -> Player {
+        let mut player = self.first;
+        for ply in 0..=index {
+            let again = ply > 0 && self.moves.get(ply - 1).is_some_and(|mv| mv.extra_turn());
+            if (ply > 0 && !again) || !self.is_playing(player, ply) {
+                player = self.after(player, ply);
+            }
+        }
+
+        player
+    }
+
+    // the player hadn't resigned yet at `ply`
+    pub fn is_playing(&self, player: Player, ply: usize) -> bool {
+        !self.out.iter().any(|&(at, p)| p == player && at <= ply)
+    }
+
+    // the next player still in the game at `ply`
+    fn after(&self, player: Player, ply: usize) -> Player {
+        let mut next = player.next(self.players);
+        while next != player && !self.is_playing(next, ply) {
+            next = next.next(self.players);
+        }
+
+        next
+    }
+
+    // the players still in, in turn order
+    pub fn playing(&self) -> Vec<Player> {
+        let ply = self.moves.len();
+        Player::ALL[..self.players]
+            .iter()
+            .copied()
+            .filter(|&player| self.is_playing(player, ply))
+            .collect()
+    }
+
+    // with two players resigning ends the game, with more the player drops out
+    // and the last one left wins
+    pub fn resign(&mut self, player: Player) {
+        self.settle();
+        if self.game_over() {
+            return;
+        }
+        if self.players == 2 {
+            self.outcome = Some(Outcome::Resigned(player));
+            return;
+        }
+
+        self.out.push((self.moves.len(), player));
+        self.offer = None;
+        match self.playing()[..] {
+            [last] => self.outcome = Some(Outcome::Outlasted(last)),
+            _ => self.turn = self.player_of(self.moves.len()),
+        }
+    }
+
+    // who played the last move, none before the first one
+    pub fn last_mover(&self) -> Option<Player> {
+        let last = self.moves.len().checked_sub(1)?;
+        Some(self.player_of(last))
+    }
+
+    // the player hasn't played that power-up yet
+    pub fn has_power(&self, player: Player, power: Power) -> bool {
+        let played = |(i, mv): (usize, &Move)| {
+            matches!(*mv, Move::Power(p, _) if p == power) && self.player_of(i) == player
+        };
+        !self.moves.iter().enumerate().any(played)
+    }
+
+    // whether the player could play the move now, the rules of the variant
+    // and the board both allow it
+    pub fn allows(&self, mv: Move, player: Player) -> bool {
+        let variant = match mv {
+            Move::Drop(_) => self.variant != Variant::Free,
+            Move::Place(..) => self.variant == Variant::Free,
+            Move::Pop(_) => self.variant == Variant::PopOut,
+            Move::Power(power, _) => {
+                self.variant == Variant::PowerUp && self.has_power(player, power)
+            }
+            Move::Swap => self.swap && self.moves.len() == 1,
+        };
+
+        variant && apply_move(&mut self.table.clone(), mv, player)
+    }
+
+    // the board as it was after the first `ply` moves
+    pub fn table_at(&self, ply: usize) -> Table {
+        let mut table = Table::new(self.table.cols(), self.table.rows());
+        for (i, &mv) in self.moves.iter().enumerate().take(ply) {
+            apply_move(&mut table, mv, self.player_of(i));
+        }
+
+        table
+    }
+
+    // plays a move for the player on turn, returns false if the column is
+    // full, the pop isn't allowed or the game is over
+    pub fn play(&mut self, mv: Move) -> bool {
+        // a disc still in the air lands at once when moves come in fast
+        self.settle();
+        if self.game_over() {
+            return false;
+        }
+
+        let player = self.turn;
+        if !self.allows(mv, player) {
+            return false;
+        }
+
+        match mv {
+            Move::Drop(col) => {
+                let Some(row) = drop_piece(&mut self.table, col, player) else {
+                    return false;
+                };
+                self.falling = Some(Falling::drop(col, row, player));
+            }
+            Move::Pop(col) => {
+                pop_piece(&mut self.table, col);
+                self.popping = Some(Popping::new(col, player));
+            }
+            Move::Power(power, col) => {
+                let Some(row) = play_power(&mut self.table, power, col, player) else {
+                    return false;
+                };
+                self.falling = Some(Falling::drop(col, row, player));
+            }
+            Move::Swap => {
+                swap_disc(&mut self.table, player);
+            }
+            Move::Place(col, row) => {
+                place_piece(&mut self.table, col, row, player);
+            }
+        }
+        self.moves.push(mv);
+
+        if let Some(clock) = &mut self.clock {
+            clock.moved(player);
+        }
+
+        // change turn, unless the move earned another one
+        self.turn = self.player_of(self.moves.len());
+        self.offer_swap();
+
+        // a placed disc is in at once, there is no fall to wait for
+        if let Move::Place(..) = mv {
+            self.landed = Some(1);
+            self.outcome = self.judge();
+        }
+
+        true
+    }
+
+    // with the swap rule the second player gets to choose once the first
+    // disc is in, and again if the game is taken back to it
+    fn offer_swap(&mut self) {
+        self.swap_offered = self.players == 2 && self.allows(Move::Swap, self.turn);
+    }
+
+    // the second player keeps their colour and plays on
+    pub fn keep_colour(&mut self) {
+        self.swap_offered = false;
+    }
+
+    // takes the last move back and gives the turn to whoever played it
+    pub fn undo(&mut self) {
+        self.settle();
+        let Some(mv) = self.moves.pop() else {
+            return;
+        };
+
+        // players who resigned after the move stay out
+        let ply = self.moves.len();
+        let player = self.player_of(ply);
+        for (at, _) in &mut self.out {
+            *at = (*at).min(ply);
+        }
+        self.turn = self.player_of(ply);
+        match mv {
+            Move::Drop(col) => {
+                if let Some((row, _)) = lift_piece(&mut self.table, col) {
+                    self.falling = Some(Falling::lift(col, row, player));
+                }
+            }
+            // the disc just goes back under the column
+            Move::Pop(col) => unpop_piece(&mut self.table, col, player),
+            // power-ups can clear whole columns or blow up a disc next door
+            // and a swap recolours one, so the board is played up again
+            Move::Power(..) | Move::Swap => self.table = self.table_at(self.moves.len()),
+            Move::Place(col, row) => self.table[row][col] = None,
+        }
+        self.outcome = None;
+        self.offer_swap();
+    }
+
+    pub fn animate(&mut self, delta: f32) {
+        let falling = self.falling.as_mut().is_some_and(|f| f.update(delta));
+        let popping = self.popping.as_mut().is_some_and(|p| p.update(delta));
+        if falling || popping {
+            self.settle();
+        }
+    }
+
+    // ends the running animation and checks whether the game is over
+    pub fn settle(&mut self) {
+        let dropped = self.falling.take().filter(|falling| !falling.out);
+        let popped = self.popping.take();
+        if let Some(falling) = dropped {
+            self.landed = Some(falling.row + 1);
+        } else if popped.is_some() {
+            self.landed = Some(1);
+        } else {
+            return;
+        }
+
+        if !self.game_over() {
+            self.outcome = self.judge();
+        }
+    }
+
+    // the outcome of the position after a move, the turn already passed on
+    fn judge(&self) -> Option<Outcome> {
+        let mover = self.last_mover()?;
+        let wrap = self.variant == Variant::Cylinder;
+        let line = |player| connected(&self.table, player, self.connect, wrap, self.exact);
+        // in misère the line loses, and only the mover can have made one
+        if self.variant == Variant::Misere && line(mover) {
+            return Some(Outcome::Completed(mover));
+        }
+
+        // a pop or an anvil can finish lines for several, then the player who
+        // moved wins, or else the first one after them
+        let mut players = self.playing();
+        let at = players.iter().position(|&p| p == mover).unwrap_or(0);
+        players.rotate_left(at);
+        if let Some(winner) = players.into_iter().find(|&p| line(p)) {
+            return Some(Outcome::Connected(winner));
+        }
+
+        // a full board goes on as long as the player on turn can pop or play
+        // a power-up
+        let can_move = (0..self.table.cols())
+            .flat_map(|col| Move::all(col, self.table.rows()))
+            .any(|mv| self.allows(mv, self.turn));
+        if !can_move {
+            return Some(Outcome::Full);
+        }
+        if self.variant != Variant::PopOut {
+            return None;
+        }
+
+        // the position with the same player on turn, counting this one
+        let seen = (0..=self.moves.len())
+            .filter(|&ply| self.player_of(ply) == self.turn && self.table_at(ply) == self.table)
+            .count();
+        (seen >= 3).then_some(Outcome::Repetition)
+    }
+
+    // runs the clock of the player on turn and returns them once their time
+    // is up, clocks start with the first move
+    pub fn tick(&mut self, delta: f32) -> Option<Player> {
+        // nobody can move while a disc falls, so it doesn't cost time
+        if self.game_over() || self.moves.is_empty() || self.is_animating() {
+            return None;
+        }
+
+        let clock = self.clock.as_mut()?;
+        clock.tick(self.turn, delta).then_some(self.turn)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn record(&self) -> crate::record::Record {
+        crate::record::Record {
+            variant: self.variant,
+            clock: self.time_control.filter(|_| self.players == 2),
+            first: self.first,
+            seed: self.seed,
+            moves: self.moves.clone(),
+            outcome: self.outcome,
+            players: self.players,
+            connect: self.connect,
+            exact: self.exact,
+            board: (self.table.cols(), self.table.rows()),
+            out: self.out.clone(),
+            swap: self.swap,
+        }
+    }
+
+    pub fn answer_offer(&mut self, accept: bool) {
+        match self.offer.take() {
+            Some(Offer::Draw(_)) if accept => self.outcome = Some(Outcome::AgreedDraw),
+            Some(Offer::Takeback(_)) if accept => self.undo(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::tests::table;
+    use crate::board::Cell;
+
+    fn game(variant: Variant, players: usize) -> Game {
+        let mut game = Game::new(variant, None, Player::Red);
+        game.players = players;
+        game.table = Table::for_players(players);
+        game
+    }
+
+    // plays the moves one after the other, every one has to be allowed
+    fn play(game: &mut Game, moves: &[Move]) {
+        for &mv in moves {
+            assert!(game.play(mv), "{:?} was turned down", mv);
+        }
+        game.settle();
+    }
+
+    #[test]
+    fn the_popper_wins_when_a_pop_makes_two_lines() {
+        let mut game = game(Variant::PopOut, 2);
+        play(&mut game, &[Move::Drop(6), Move::Drop(6)]);
+        // popping red's disc drops yellow into a line and red into one above
+        game.table = table(
+            "
+            .......
+            .......
+            .......
+            r......
+            yrrr...
+            ryyy...
+            ",
+        );
+        play(&mut game, &[Move::Pop(0)]);
+        assert!(connected(&game.table, Player::Yellow, 4, false, false));
+        assert!(connected(&game.table, Player::Red, 4, false, false));
+        assert_eq!(game.outcome, Some(Outcome::Connected(Player::Red)));
+    }
+
+    #[test]
+    fn lines_for_others_go_to_the_next_in_turn() {
+        let mut game = game(Variant::PopOut, 3);
+        // green moved last, red comes after them before yellow does
+        game.moves = vec![Move::Drop(0), Move::Drop(1), Move::Drop(2)];
+        game.table = table(
+            "
+            .........
+            .........
+            .........
+            y...r....
+            y...r....
+            y...r....
+            y...r....
+            ",
+        );
+        assert_eq!(game.last_mover(), Some(Player::Green));
+        assert_eq!(game.judge(), Some(Outcome::Connected(Player::Red)));
+
+        // yellow moved last, so their own line counts first
+        game.moves.truncate(2);
+        assert_eq!(game.judge(), Some(Outcome::Connected(Player::Yellow)));
+    }
+
+    #[test]
+    fn popout_draws_on_the_third_repetition() {
+        let mut game = game(Variant::PopOut, 2);
+        let round = [Move::Drop(0), Move::Drop(1), Move::Pop(0), Move::Pop(1)];
+        // the empty board with red on turn comes back after every round
+        play(&mut game, &round);
+        assert_eq!(game.outcome, None);
+        play(&mut game, &round);
+        assert_eq!(game.outcome, Some(Outcome::Repetition));
+    }
+
+    #[test]
+    fn only_own_discs_pop_and_only_in_popout() {
+        let mut popout = game(Variant::PopOut, 2);
+        play(&mut popout, &[Move::Drop(0)]);
+        assert!(!popout.play(Move::Pop(0)));
+        assert!(!popout.play(Move::Pop(1)));
+
+        let mut classic = game(Variant::Classic, 2);
+        play(&mut classic, &[Move::Drop(0), Move::Drop(1)]);
+        assert!(!classic.play(Move::Pop(0)));
+    }
+
+    #[test]
+    fn x2_gives_another_turn() {
+        let mut game = game(Variant::PowerUp, 2);
+        play(&mut game, &[Move::Drop(0), Move::Power(Power::Double, 1)]);
+        assert_eq!(game.turn, Player::Yellow);
+        play(&mut game, &[Move::Drop(2)]);
+        assert_eq!(game.player_of(2), Player::Yellow);
+        assert_eq!(game.turn, Player::Red);
+        assert!(!game.has_power(Player::Yellow, Power::Double));
+        assert!(game.has_power(Player::Red, Power::Double));
+
+        game.undo();
+        game.undo();
+        assert_eq!(game.turn, Player::Yellow);
+        assert!(game.has_power(Player::Yellow, Power::Double));
+    }
+
+    #[test]
+    fn turns_skip_players_who_resigned() {
+        let mut game = game(Variant::Classic, 3);
+        play(&mut game, &[Move::Drop(0)]);
+        game.resign(Player::Yellow);
+        assert_eq!(game.outcome, None);
+        assert_eq!(game.turn, Player::Green);
+        play(&mut game, &[Move::Drop(1), Move::Drop(2)]);
+        assert_eq!(game.player_of(1), Player::Green);
+        assert_eq!(game.player_of(2), Player::Red);
+        assert_eq!(game.turn, Player::Green);
+        assert_eq!(game.playing(), vec![Player::Red, Player::Green]);
+
+        // taking moves back doesn't bring them back in
+        game.undo();
+        game.undo();
+        assert_eq!(game.turn, Player::Green);
+        assert_eq!(game.playing(), vec![Player::Red, Player::Green]);
+
+        game.resign(Player::Green);
+        assert_eq!(game.outcome, Some(Outcome::Outlasted(Player::Red)));
+    }
+
+    #[test]
+    fn resigning_out_of_turn() {
+        let mut four = game(Variant::Classic, 4);
+        play(&mut four, &[Move::Drop(0)]);
+        // yellow is on turn, green leaving doesn't change that
+        four.resign(Player::Green);
+        assert_eq!(four.turn, Player::Yellow);
+        play(&mut four, &[Move::Drop(1)]);
+        assert_eq!(four.turn, Player::Purple);
+
+        // with two it is over at once
+        let mut two = game(Variant::Classic, 2);
+        two.resign(Player::Yellow);
+        assert_eq!(two.outcome, Some(Outcome::Resigned(Player::Yellow)));
+        assert_eq!(two.outcome.and_then(Outcome::winner), Some(Player::Red));
+    }
+
+    #[test]
+    fn swap_takes_over_the_first_disc() {
+        let mut game = game(Variant::Classic, 2);
+        game.swap = true;
+        play(&mut game, &[Move::Drop(3)]);
+        assert!(game.swap_offered);
+        play(&mut game, &[Move::Swap]);
+        assert!(!game.swap_offered);
+        assert_eq!(game.table[5][3], Some(Cell::Disc(Player::Yellow)));
+        // red moves again, against yellow's disc now
+        assert_eq!(game.turn, Player::Red);
+        assert!(!game.allows(Move::Swap, Player::Red));
+    }
+
+    #[test]
+    fn undoing_a_swap_offers_it_again() {
+        let mut game = game(Variant::Classic, 2);
+        game.swap = true;
+        play(&mut game, &[Move::Drop(3), Move::Swap, Move::Drop(4)]);
+        game.undo();
+        game.undo();
+        assert_eq!(game.table[5][3], Some(Cell::Disc(Player::Red)));
+        assert_eq!(game.turn, Player::Yellow);
+        assert!(game.swap_offered);
+
+        game.keep_colour();
+        assert!(!game.swap_offered);
+        play(&mut game, &[Move::Drop(4)]);
+        assert!(!game.swap_offered);
+        // and back to the first disc once more
+        game.undo();
+        assert!(game.swap_offered);
+        game.undo();
+        assert!(!game.swap_offered);
+    }
+
+    #[test]
+    fn no_swap_without_the_rule_or_with_more_players() {
+        let mut off = game(Variant::Classic, 2);
+        play(&mut off, &[Move::Drop(3)]);
+        assert!(!off.swap_offered);
+        assert!(!off.play(Move::Swap));
+
+        let mut three = game(Variant::Classic, 3);
+        three.swap = true;
+        play(&mut three, &[Move::Drop(3)]);
+        assert!(!three.swap_offered);
+    }
+
+    #[test]
+    fn placed_discs_are_judged_at_once() {
+        let mut game = game(Variant::Free, 2);
+        game.connect = 3;
+        game.exact = true;
+        let moves = [
+            Move::Place(0, 0),
+            Move::Place(6, 5),
+            Move::Place(1, 0),
+            Move::Place(5, 5),
+            Move::Place(3, 0),
+            Move::Place(4, 5),
+        ];
+        for mv in moves {
+            assert!(game.play(mv));
+        }
+        assert_eq!(game.outcome, Some(Outcome::Connected(Player::Yellow)));
+        assert!(!game.play(Move::Place(2, 0)));
+
+        // red filling their gap makes four, which isn't a line of exactly three
+        game.undo();
+        assert!(game.play(Move::Place(0, 3)));
+        assert!(game.play(Move::Place(2, 0)));
+        assert_eq!(game.outcome, None);
+        assert!(!game.play(Move::Drop(2)));
+    }
+}
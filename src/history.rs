@@ -0,0 +1,201 @@
+// The move list beside the board. Clicking a move shows the board as it was
+// right after it, read only, and LEFT and RIGHT step back and forth from
+// there. The game itself goes on meanwhile, "Return to live" or ESCAPE brings
+// the board back to it.
+
+use notan::draw::*;
+use notan::math::{vec2, Vec2};
+use notan::prelude::*;
+
+use crate::board::Move;
+use crate::fog;
+use crate::input::Command;
+use crate::layout::{Layout, HEIGHT, PANEL_HEIGHT, SIDE_WIDTH, WIDTH};
+use crate::State;
+
+const TOP: f32 = 60.0;
+const ROW_HEIGHT: f32 = 24.0;
+// rows that fit above the button
+const SHOWN: usize = 26;
+const BUTTON_SIZE: Vec2 = Vec2::new(160.0, 40.0);
+
+// takes the clicks and keys meant for the move list
+pub fn update(app: &App, state: &mut State) {
+    let moves = state.game.moves.len();
+    // a new game or a takeback can leave nothing to look back at, and the
+    // fog of war would lift looking back
+    let fog = fog::is_active(state);
+    state.browse = state
+        .browse
+        .filter(|&ply| moves > 0 && ply <= moves && !fog);
+    if fog {
+        return;
+    }
+
+    if let Some((mx, my)) = state.input.clicked(app) {
+        let (pos, size) = state.layout.side();
+        if (pos.x..pos.x + size.x).contains(&mx) && (pos.y..pos.y + size.y).contains(&my) {
+            state.input.take_tap();
+            if let Some(ply) = row_at(state, mx, my) {
+                state.browse = Some(ply);
+            } else if state.browse.is_some() && on_button(&state.layout, mx, my) {
+                state.browse = None;
+            }
+        }
+    }
+
+    let Some(ply) = state.browse else {
+        return;
+    };
+
+    if state.input.take(Command::Back) {
+        state.browse = None;
+    } else if state.input.take(Command::Left) {
+        state.browse = Some(ply.saturating_sub(1));
+    } else if state.input.take(Command::Right) {
+        state.browse = Some((ply + 1).min(moves));
+    }
+}
+
+// ply 0 is the empty board at the top, then one row per move
+fn rows(state: &State) -> std::ops::Range<usize> {
+    let count = state.game.moves.len() + 1;
+    if count == 1 {
+        return 0..0;
+    }
+
+    // the newest moves are in sight, or the ones around the shown board
+    let focus = state.browse.unwrap_or(count - 1);
+    let first = count
+        .saturating_sub(SHOWN)
+        .min(focus.saturating_sub(SHOWN / 2));
+    first..count.min(first + SHOWN)
+}
+
+// top left corner and size of the `i`th row on the screen
+fn row_rect(layout: &Layout, i: usize) -> (Vec2, Vec2) {
+    let y = TOP + i as f32 * ROW_HEIGHT;
+    (
+        layout.point(WIDTH + 10.0, y),
+        vec2(SIDE_WIDTH - 20.0, ROW_HEIGHT) * layout.scale,
+    )
+}
+
+fn row_at(state: &State, mx: f32, my: f32) -> Option<usize> {
+    rows(state).enumerate().find_map(|(i, ply)| {
+        let (pos, size) = row_rect(&state.layout, i);
+        let inside = (pos.x..pos.x + size.x).contains(&mx) && (pos.y..pos.y + size.y).contains(&my);
+        inside.then_some(ply)
+    })
+}
+
+fn button_rect(layout: &Layout) -> (Vec2, Vec2) {
+    let x = WIDTH + (SIDE_WIDTH - BUTTON_SIZE.x) * 0.5;
+    let y = HEIGHT + PANEL_HEIGHT - BUTTON_SIZE.y - 20.0;
+    (layout.point(x, y), BUTTON_SIZE * layout.scale)
+}
+
+fn on_button(layout: &Layout, mx: f32, my: f32) -> bool {
+    let (pos, size) = button_rect(layout);
+    (pos.x..pos.x + size.x).contains(&mx) && (pos.y..pos.y + size.y).contains(&my)
+}
+
+pub fn draw(draw: &mut Draw, state: &State) {
+    let layout = &state.layout;
+    let font = &state.font;
+    let style = state.settings.style();
+
+    let (pos, size) = layout.side();
+    draw.line((pos.x, pos.y), (pos.x, pos.y + size.y))
+        .width(layout.len(2.0))
+        .color(style.theme.grid);
+
+    let pos = layout.point(WIDTH + SIDE_WIDTH * 0.5, TOP * 0.5);
+    draw.text(font, "Moves")
+        .position(pos.x, pos.y)
+        .size(layout.len(24.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    if state.game.moves.is_empty() {
+        let pos = layout.point(WIDTH + SIDE_WIDTH * 0.5, TOP + ROW_HEIGHT * 0.5);
+        draw.text(font, "none yet")
+            .position(pos.x, pos.y)
+            .size(layout.len(16.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK)
+            .alpha(0.6);
+        return;
+    }
+
+    for (i, ply) in rows(state).enumerate() {
+        let (pos, size) = row_rect(layout, i);
+        if state.browse == Some(ply) {
+            draw.rect((pos.x, pos.y), (size.x, size.y))
+                .color(style.theme.grid)
+                .alpha(0.3);
+        }
+
+        let middle = pos.y + size.y * 0.5;
+        if ply == 0 {
+            draw.text(font, "start")
+                .position(pos.x + layout.len(12.0), middle)
+                .size(layout.len(16.0))
+                .v_align_middle()
+                .color(Color::BLACK);
+            continue;
+        }
+
+        let index = ply - 1;
+        draw.text(font, &format!("{}.", ply))
+            .position(pos.x + layout.len(44.0), middle)
+            .size(layout.len(16.0))
+            .h_align_right()
+            .v_align_middle()
+            .color(Color::BLACK);
+
+        let player = state.game.player_of(index);
+        let disc = vec2(pos.x + layout.len(62.0), middle);
+        style.draw_disc(draw, player, layout.len(8.0), disc);
+
+        let text = match state.game.moves[index] {
+            _ if fog::hides(state, player) => "hidden".to_string(),
+            Move::Drop(col) => format!("column {}", col + 1),
+            Move::Pop(col) => format!("pop {}", col + 1),
+            Move::Power(power, col) => format!("{} {}", power.name(), col + 1),
+            Move::Swap => "swap".to_string(),
+            // lettered columns and rows counted up from the bottom, like a1
+            Move::Place(col, row) => {
+                let rows = state.game.table.rows();
+                format!("cell {}{}", (b'a' + col as u8) as char, rows - row)
+            }
+        };
+        draw.text(font, &text)
+            .position(pos.x + layout.len(80.0), middle)
+            .size(layout.len(16.0))
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    if state.browse.is_none() {
+        return;
+    }
+
+    let (pos, size) = button_rect(layout);
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .color(Color::BLUE)
+        .alpha(0.3);
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .stroke_color(Color::BLUE)
+        .stroke(layout.len(2.0));
+
+    let center = pos + size * 0.5;
+    draw.text(font, "Return to live")
+        .position(center.x, center.y)
+        .size(layout.len(18.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+}
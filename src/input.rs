@@ -0,0 +1,586 @@
+// Keyboard and gamepad controls. A column cursor moves with the arrow keys,
+// the number keys (0 is the tenth column) or the D-pad, and ENTER or A drops
+// a disc in it. In PopOut
+// P or the west button pops the bottom disc of the column instead, as does a
+// right click or a click with SHIFT held.
+// Every key and button goes through `Bindings`, so they can be changed, e.g.
+// `--bind drop=Space,Return`. Gamepads need the `gamepad` feature.
+// On touch screens a tap selects a column and a second tap on it drops the
+// disc, or the disc can be dragged to a column and dropped by letting go.
+// Swiping down off the board from a bottom disc pops it.
+// With gravity off the cursor is on a cell: UP and DOWN move it between rows,
+// and clicks and taps pick the cell under them.
+
+use notan::prelude::*;
+
+use crate::board::{Move, COLS, MAX_COLS, ROWS};
+use crate::layout::Layout;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Command {
+    Left,
+    Right,
+    Column(usize),
+    Drop,
+    // takes the cursor column's bottom disc out in PopOut
+    Pop,
+    // picks the next power-up in the PowerUp variant
+    Power,
+    // turns the board of the cylinder variant a column
+    TurnLeft,
+    TurnRight,
+    // new game or rematch once a game is over
+    Restart,
+    // moving through menus, BACK also pauses the game
+    Up,
+    Down,
+    Select,
+    Back,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "left" => Some(Command::Left),
+            "right" => Some(Command::Right),
+            "drop" => Some(Command::Drop),
+            "pop" => Some(Command::Pop),
+            "power" => Some(Command::Power),
+            "turnleft" => Some(Command::TurnLeft),
+            "turnright" => Some(Command::TurnRight),
+            "restart" => Some(Command::Restart),
+            "up" => Some(Command::Up),
+            "down" => Some(Command::Down),
+            "select" => Some(Command::Select),
+            "back" => Some(Command::Back),
+            _ => {
+                let col = name.strip_prefix("column")?.parse::<usize>().ok()?;
+                (1..=MAX_COLS)
+                    .contains(&col)
+                    .then(|| Command::Column(col - 1))
+            }
+        }
+    }
+}
+
+// keys that can be bound, looked up by their notan name
+const KEYS: &[KeyCode] = &[
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Left,
+    KeyCode::Up,
+    KeyCode::Right,
+    KeyCode::Down,
+    KeyCode::Return,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Back,
+    KeyCode::Escape,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadEnter,
+];
+
+const DIGITS: [KeyCode; MAX_COLS] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+];
+
+const NUMPAD: [KeyCode; MAX_COLS] = [
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::Numpad0,
+];
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+#[cfg(feature = "gamepad")]
+fn button_from_name(name: &str) -> Option<gilrs::Button> {
+    use gilrs::Button;
+
+    const BUTTONS: &[Button] = &[
+        Button::South,
+        Button::East,
+        Button::North,
+        Button::West,
+        Button::LeftTrigger,
+        Button::RightTrigger,
+        Button::Select,
+        Button::Start,
+        Button::DPadUp,
+        Button::DPadDown,
+        Button::DPadLeft,
+        Button::DPadRight,
+    ];
+
+    BUTTONS
+        .iter()
+        .copied()
+        .find(|button| format!("{:?}", button).eq_ignore_ascii_case(name))
+}
+
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: Vec<(KeyCode, Command)>,
+    #[cfg(feature = "gamepad")]
+    buttons: Vec<(gilrs::Button, Command)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut keys = vec![
+            (KeyCode::Left, Command::Left),
+            (KeyCode::Right, Command::Right),
+            (KeyCode::Return, Command::Drop),
+            (KeyCode::NumpadEnter, Command::Drop),
+            (KeyCode::Down, Command::Drop),
+            (KeyCode::P, Command::Pop),
+            (KeyCode::Space, Command::Restart),
+            (KeyCode::Up, Command::Up),
+            (KeyCode::Up, Command::Power),
+            (KeyCode::Q, Command::TurnLeft),
+            (KeyCode::E, Command::TurnRight),
+            (KeyCode::Down, Command::Down),
+            (KeyCode::Return, Command::Select),
+            (KeyCode::NumpadEnter, Command::Select),
+            (KeyCode::Escape, Command::Back),
+        ];
+
+        for col in 0..MAX_COLS {
+            keys.push((DIGITS[col], Command::Column(col)));
+            keys.push((NUMPAD[col], Command::Column(col)));
+        }
+
+        Bindings {
+            keys,
+            #[cfg(feature = "gamepad")]
+            buttons: vec![
+                (gilrs::Button::DPadLeft, Command::Left),
+                (gilrs::Button::DPadRight, Command::Right),
+                (gilrs::Button::South, Command::Drop),
+                (gilrs::Button::West, Command::Pop),
+                (gilrs::Button::Start, Command::Restart),
+                (gilrs::Button::DPadUp, Command::Up),
+                (gilrs::Button::DPadUp, Command::Power),
+                (gilrs::Button::LeftTrigger, Command::TurnLeft),
+                (gilrs::Button::RightTrigger, Command::TurnRight),
+                (gilrs::Button::DPadDown, Command::Down),
+                (gilrs::Button::South, Command::Select),
+                (gilrs::Button::East, Command::Back),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    // `<command>=<key>[,<key>...]` replaces every binding of the command,
+    // gamepad buttons are named like `DPadLeft` or `South`
+    pub fn rebind(&mut self, spec: &str) -> Result<(), String> {
+        let (name, inputs) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected <command>=<key> in '{}'", spec))?;
+        let command =
+            Command::from_name(name).ok_or_else(|| format!("unknown command '{}'", name))?;
+
+        let mut keys = vec![];
+        #[cfg(feature = "gamepad")]
+        let mut buttons = vec![];
+        for input in inputs.split(',') {
+            #[cfg(feature = "gamepad")]
+            if let Some(button) = button_from_name(input) {
+                buttons.push((button, command));
+                continue;
+            }
+
+            let key = key_from_name(input).ok_or_else(|| format!("unknown key '{}'", input))?;
+            keys.push((key, command));
+        }
+
+        self.keys.retain(|&(_, bound)| bound != command);
+        self.keys.extend(keys);
+        #[cfg(feature = "gamepad")]
+        {
+            self.buttons.retain(|&(_, bound)| bound != command);
+            self.buttons.extend(buttons);
+        }
+
+        Ok(())
+    }
+}
+
+// a finger on the screen, see `Input::event`
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Finger {
+    id: u64,
+    // it went down on the board, otherwise it is a tap for the panel
+    on_board: bool,
+    // it went down on the column that was already selected
+    confirms: bool,
+    dragged: bool,
+    // it went down on the bottom row, a swipe down from there pops the disc
+    bottom: bool,
+}
+
+// browsers send mouse events after touches too, those are ignored for a while
+const TOUCH_GRACE: f32 = 0.5;
+
+pub struct Input {
+    bindings: Bindings,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<gilrs::Gilrs>,
+    commands: Vec<Command>,
+    cursor: usize,
+    // the row of the cursor, only used with gravity off
+    row: usize,
+    // the cursor hides again once the mouse moves
+    cursor_shown: bool,
+    mouse: (f32, f32),
+    finger: Option<Finger>,
+    // a column was tapped and waits for the confirming tap
+    selected: bool,
+    touched: Vec<Command>,
+    tap: Option<(f32, f32)>,
+    taps: Vec<(f32, f32)>,
+    since_touch: f32,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Self {
+        Input {
+            bindings,
+            #[cfg(feature = "gamepad")]
+            gamepads: gilrs::Gilrs::new()
+                .map_err(|e| eprintln!("gamepads are not available: {}", e))
+                .ok(),
+            commands: vec![],
+            cursor: COLS / 2,
+            row: ROWS / 2,
+            cursor_shown: false,
+            mouse: (0.0, 0.0),
+            finger: None,
+            selected: false,
+            touched: vec![],
+            tap: None,
+            taps: vec![],
+            since_touch: TOUCH_GRACE,
+        }
+    }
+
+    // touches come in as events, they are turned into commands on the next update;
+    // without a board every touch is a tap
+    pub fn event(&mut self, event: &Event, board: Option<&Layout>) {
+        match *event {
+            Event::TouchStart { id, x, y } if self.finger.is_none() => {
+                self.since_touch = 0.0;
+                let cell = board.and_then(|layout| layout.cell_at(x, y));
+                let col = cell.map(|(col, _)| col);
+                // without gravity the second tap has to be on the same cell
+                let same = board.is_some_and(|layout| layout.gravity)
+                    || cell.map(|(_, row)| row) == Some(self.row);
+                let confirms =
+                    self.selected && self.cursor_shown && col == Some(self.cursor) && same;
+                let bottom = col.is_some()
+                    && board.is_some_and(|layout| {
+                        let (pos, size) = layout.board();
+                        y >= pos.y + size.y - layout.tile().y
+                    });
+                if let Some((col, row)) = cell {
+                    self.cursor = col;
+                    self.row = row;
+                    self.cursor_shown = true;
+                }
+
+                self.finger = Some(Finger {
+                    id,
+                    on_board: col.is_some(),
+                    confirms,
+                    dragged: false,
+                    bottom,
+                });
+            }
+            Event::TouchMove { id, x, y } => {
+                let Some(finger) = self.finger.as_mut().filter(|finger| finger.id == id) else {
+                    return;
+                };
+
+                self.since_touch = 0.0;
+                if !finger.on_board {
+                    return;
+                }
+
+                // dragging off the board puts the disc back
+                match board.and_then(|layout| layout.cell_at(x, y)) {
+                    Some((col, row)) => {
+                        let gravity = board.is_some_and(|layout| layout.gravity);
+                        finger.dragged |= col != self.cursor || (!gravity && row != self.row);
+                        self.cursor = col;
+                        self.row = row;
+                        self.cursor_shown = true;
+                    }
+                    None => {
+                        finger.dragged = true;
+                        self.cursor_shown = false;
+                    }
+                }
+            }
+            Event::TouchEnd { id, x, y } => {
+                let Some(finger) = self.finger.filter(|finger| finger.id == id) else {
+                    return;
+                };
+
+                self.finger = None;
+                self.since_touch = 0.0;
+                if !finger.on_board {
+                    self.taps.push((x, y));
+                    return;
+                }
+
+                let below = board.is_some_and(|layout| {
+                    let (pos, size) = layout.board();
+                    y > pos.y + size.y
+                });
+                if finger.bottom && below {
+                    self.selected = false;
+                    self.cursor_shown = true;
+                    self.touched.push(Command::Pop);
+                } else if !self.cursor_shown {
+                    self.selected = false;
+                } else if finger.dragged || finger.confirms {
+                    self.selected = false;
+                    self.touched.push(Command::Drop);
+                } else {
+                    self.selected = true;
+                    self.taps.push((x, y));
+                }
+            }
+            Event::TouchCancel { id, .. } if self.finger.is_some_and(|finger| finger.id == id) => {
+                self.finger = None;
+                self.selected = false;
+                self.cursor_shown = false;
+            }
+            _ => {}
+        }
+    }
+
+    // reads this frame's commands, keys are left alone while typing; the
+    // cursor stays on the board of the layout
+    pub fn update(&mut self, app: &App, typing: bool, layout: &Layout) {
+        self.commands.clear();
+        self.commands.append(&mut self.touched);
+        self.tap = self.taps.pop();
+        self.taps.clear();
+        self.since_touch += app.timer.delta_f32();
+
+        if !typing {
+            for &(key, command) in &self.bindings.keys {
+                if app.keyboard.was_pressed(key) {
+                    self.commands.push(command);
+                }
+            }
+        }
+
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            while let Some(gilrs::Event { event, .. }) = gamepads.next_event() {
+                let gilrs::EventType::ButtonPressed(pressed, _) = event else {
+                    continue;
+                };
+
+                for &(button, command) in &self.bindings.buttons {
+                    if button == pressed {
+                        self.commands.push(command);
+                    }
+                }
+            }
+        }
+
+        // DOWN drops a disc with gravity, without it it moves down a row
+        let free = !layout.gravity;
+        if free && self.pressed(Command::Down) && !self.pressed(Command::Select) {
+            self.commands.retain(|&command| command != Command::Drop);
+        }
+
+        let (cols, rows) = (layout.cols, layout.rows);
+        let shown = self.cursor_shown;
+        for &command in &self.commands {
+            match command {
+                // in the order the columns are drawn, the board may be turned
+                Command::Left => self.cursor = layout.step(self.cursor, -1),
+                Command::Right => self.cursor = layout.step(self.cursor, 1),
+                Command::Up if free => self.row = self.row.saturating_sub(1),
+                Command::Down if free => self.row += 1,
+                Command::Column(col) if col < cols => self.cursor = col,
+                Command::Drop | Command::Pop => {}
+                _ => continue,
+            }
+            self.selected &= command == Command::Drop || command == Command::Pop;
+            self.cursor_shown = true;
+        }
+        self.cursor = self.cursor.min(cols - 1);
+        self.row = self.row.min(rows - 1);
+
+        // the first press only brings the cursor up
+        if !shown {
+            self.commands
+                .retain(|&command| command != Command::Drop && command != Command::Pop);
+        }
+
+        let mouse = app.mouse.position();
+        if mouse != self.mouse && !self.touching() {
+            self.mouse = mouse;
+            self.cursor_shown = false;
+            self.selected = false;
+        }
+    }
+
+    pub fn pressed(&self, command: Command) -> bool {
+        self.commands.contains(&command)
+    }
+
+    // like `pressed`, but nothing after this sees the command
+    pub fn take(&mut self, command: Command) -> bool {
+        let pressed = self.pressed(command);
+        self.commands.retain(|&c| c != command);
+        pressed
+    }
+
+    // the tap was used for something off the board
+    pub fn take_tap(&mut self) {
+        self.tap = None;
+    }
+
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor_shown.then_some(self.cursor)
+    }
+
+    // the cell of the cursor, for boards without gravity
+    pub fn cursor_cell(&self) -> Option<(usize, usize)> {
+        self.cursor_shown.then_some((self.cursor, self.row))
+    }
+
+    // a column was tapped once and the next tap on it drops the disc
+    pub fn is_selected(&self) -> bool {
+        self.selected && self.cursor_shown
+    }
+
+    pub fn tapped(&self) -> bool {
+        self.tap.is_some()
+    }
+
+    fn touching(&self) -> bool {
+        self.finger.is_some() || self.since_touch < TOUCH_GRACE
+    }
+
+    // where the mouse was clicked or the screen tapped this frame
+    pub fn clicked(&self, app: &App) -> Option<(f32, f32)> {
+        if app.mouse.was_pressed(MouseButton::Left) && !self.touching() {
+            return Some(app.mouse.position());
+        }
+
+        self.tap
+    }
+
+    // the column a disc should go into this frame, clicked or dropped from the cursor
+    pub fn chosen_column(&self, app: &App, layout: &Layout) -> Option<usize> {
+        match self.chosen_move(app, layout)? {
+            Move::Drop(col) => Some(col),
+            Move::Pop(_) | Move::Power(..) | Move::Swap | Move::Place(..) => None,
+        }
+    }
+
+    // like `chosen_column`, but POP, or in PopOut a right or SHIFT click,
+    // takes the bottom disc out instead
+    pub fn chosen_move(&self, app: &App, layout: &Layout) -> Option<Move> {
+        if self.pressed(Command::Drop) && !layout.gravity {
+            return Some(Move::Place(self.cursor, self.row));
+        }
+        if self.pressed(Command::Drop) {
+            return Some(Move::Drop(self.cursor));
+        }
+        if self.pressed(Command::Pop) {
+            return Some(Move::Pop(self.cursor));
+        }
+        if self.touching() {
+            return None;
+        }
+
+        let shift = app.keyboard.shift();
+        let (mx, my) = app.mouse.position();
+        let col = layout.column_at(mx, my)?;
+        let pop = app.mouse.was_pressed(MouseButton::Right)
+            || (shift && app.mouse.was_pressed(MouseButton::Left));
+        if pop && layout.pops {
+            Some(Move::Pop(col))
+        } else if app.mouse.was_pressed(MouseButton::Left) && !layout.gravity {
+            let (col, row) = layout.cell_at(mx, my)?;
+            Some(Move::Place(col, row))
+        } else if app.mouse.was_pressed(MouseButton::Left) {
+            Some(Move::Drop(col))
+        } else {
+            None
+        }
+    }
+}
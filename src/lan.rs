@@ -0,0 +1,423 @@
+// Direct games over the local network, without a server. One side hosts on
+// a TCP port and is authoritative, the other joins by IP address.
+// The guest opens with `HELLO <version> <rules>`, the host checks both and
+// answers with the guest's colour, who moves first, the time control and its
+// own rules, which the guest checks in turn.
+// The host also keeps the time and calls it when a clock runs out, and
+// confirms offers and their answers like moves, so both sides agree on them.
+
+use std::io;
+use std::net::TcpListener;
+
+use notan::prelude::*;
+
+use crate::board::{coin_flip, Offer, Outcome, Player};
+use crate::client::Connection;
+use crate::clock::TimeControl;
+use crate::game::{Game, Rules};
+use crate::input::Command;
+use crate::panel::{self, Action, Controls};
+use crate::protocol::{PeerMessage, DEFAULT_PORT, VERSION};
+use crate::sound::Effect;
+use crate::State;
+
+const HOST: Player = Player::Red;
+const GUEST: Player = Player::Yellow;
+
+pub struct Lan {
+    // open while the host is waiting for a guest
+    listener: Option<TcpListener>,
+    conn: Option<Connection>,
+    host: bool,
+    me: Player,
+    ready: bool,
+    status: String,
+}
+
+impl Lan {
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Lan {
+            listener: Some(listener),
+            conn: None,
+            host: true,
+            me: HOST,
+            ready: false,
+            status: format!("waiting for a player on port {}", port),
+        })
+    }
+
+    pub fn join(addr: &str, rules: Rules) -> io::Result<Self> {
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{}:{}", addr, DEFAULT_PORT)
+        };
+
+        let mut conn = Connection::open(&addr)?;
+        conn.send(&PeerMessage::Hello {
+            version: VERSION,
+            rules: Some(rules),
+        });
+
+        Ok(Lan {
+            listener: None,
+            conn: Some(conn),
+            host: false,
+            me: GUEST,
+            ready: false,
+            status: format!("waiting for {}", addr),
+        })
+    }
+
+    fn send(&mut self, msg: &PeerMessage) {
+        if let Some(conn) = &mut self.conn {
+            conn.send(msg);
+        }
+    }
+
+    // text shown under the board
+    pub fn status(&self) -> String {
+        format!("LAN - playing {} - {}", self.me.name(), self.status)
+    }
+
+    pub fn controls(&self) -> Controls {
+        if self.ready {
+            Controls::Player(self.me)
+        } else {
+            Controls::Watching
+        }
+    }
+
+    pub fn end_hint(&self) -> &'static str {
+        if self.host {
+            "Press SPACE for a rematch"
+        } else {
+            "Press SPACE to ask for a rematch"
+        }
+    }
+
+    fn update(&mut self, app: &mut App, state: &mut State) {
+        if let Some(listener) = &self.listener {
+            if let Ok((stream, _)) = listener.accept() {
+                if self.ready {
+                    if let Ok(mut conn) = Connection::from_stream(stream) {
+                        conn.send(&PeerMessage::Reject(
+                            "a game is already running".to_string(),
+                        ));
+                    }
+                } else if let Ok(conn) = Connection::from_stream(stream) {
+                    self.conn = Some(conn);
+                }
+            }
+        }
+
+        let messages = match &mut self.conn {
+            Some(conn) => conn.poll::<PeerMessage>(),
+            None => vec![],
+        };
+
+        for msg in messages {
+            self.on_message(state, msg);
+        }
+
+        if self.conn.as_ref().is_some_and(|conn| conn.is_closed()) {
+            self.conn = None;
+            self.ready = false;
+            self.status = if self.host {
+                "the other player left, waiting for a new one".to_string()
+            } else {
+                "the host left".to_string()
+            };
+        }
+
+        if !self.ready {
+            // the host picks the clock while waiting for a player
+            if self.host && app.keyboard.was_pressed(KeyCode::T) {
+                state.game.time_control = TimeControl::cycle(state.game.time_control);
+                state.game.start(state.game.turn);
+            }
+            return;
+        }
+
+        if let Some(player) = state.game.tick(app.timer.delta_f32()) {
+            if self.host {
+                state.game.offer = None;
+                state.game.outcome = Some(Outcome::Timeout(player));
+                self.send(&PeerMessage::Timeout { player });
+            }
+        }
+
+        let typing = state.chat.is_typing();
+        if let Some(text) = state.chat.update(&app.keyboard) {
+            state.chat.push(format!("you: {}", text));
+            self.send(&PeerMessage::Chat { text });
+        }
+        if typing || state.chat.is_typing() {
+            return;
+        }
+
+        if state.game.game_over() {
+            if state.input.pressed(Command::Restart) {
+                if self.host {
+                    let first = coin_flip(&mut state.rng);
+                    state.game.start(first);
+                    self.send(&PeerMessage::Start { first });
+                } else {
+                    self.send(&PeerMessage::Rematch);
+                }
+            }
+            return;
+        }
+
+        if state.game.is_animating() {
+            return;
+        }
+
+        // resigning applies on both sides at once, offers and answers wait for
+        // the host like moves do
+        let player = self.me;
+        if let Some((mx, my)) = state.input.clicked(app) {
+            let msg = match panel::action_at(state, self.controls(), mx, my) {
+                Some(Action::Resign) => {
+                    state.game.outcome = Some(Outcome::Resigned(player));
+                    self.send(&PeerMessage::Resign { player });
+                    return;
+                }
+                Some(Action::OfferDraw) => Some(PeerMessage::OfferDraw { player }),
+                Some(Action::Takeback) => Some(PeerMessage::RequestTakeback { player }),
+                Some(action @ (Action::Accept | Action::Decline)) => {
+                    let accept = action == Action::Accept;
+                    match state.game.offer {
+                        Some(Offer::Draw(_)) => Some(PeerMessage::AnswerDraw { accept }),
+                        Some(Offer::Takeback(_)) => Some(PeerMessage::AnswerTakeback { accept }),
+                        None => None,
+                    }
+                }
+                // the swap rule is only for hot seat games
+                Some(Action::Swap | Action::Keep) | None => None,
+            };
+
+            if let Some(msg) = msg {
+                if !self.host || confirm(&mut state.game, &msg, Some(player)) {
+                    self.send(&msg);
+                }
+                return;
+            }
+        }
+
+        // no moves while looking back through the game
+        if state.game.offer.is_some() || state.game.turn != player || state.browse.is_some() {
+            return;
+        }
+
+        let Some(mv) = state.input.chosen_move(app, &state.layout) else {
+            return;
+        };
+        let mv = crate::powers::arm(state, mv);
+
+        if !self.host {
+            // wait for the host to confirm it
+            self.send(&PeerMessage::Move { player, mv });
+        } else if state.game.play(mv) {
+            self.send(&PeerMessage::Move { player, mv });
+        } else {
+            state.sounds.queue(Effect::Invalid);
+        }
+    }
+
+    fn on_message(&mut self, state: &mut State, msg: PeerMessage) {
+        match msg {
+            PeerMessage::Hello { version, rules } if self.host => {
+                let host_rules = state.game.rules();
+                let reject = if version != VERSION {
+                    Some(format!("host speaks version {}", VERSION))
+                } else if rules != Some(host_rules) {
+                    Some(format!("host plays {}", host_rules.describe()))
+                } else {
+                    None
+                };
+
+                if let Some(text) = reject {
+                    self.status = format!("rejected a player: {}", text);
+                    self.send(&PeerMessage::Reject(text));
+                    self.conn = None;
+                    return;
+                }
+
+                // the host decides who starts
+                let first = coin_flip(&mut state.rng);
+                state.game.start(first);
+                self.ready = true;
+                self.status = "game on".to_string();
+                self.send(&PeerMessage::Accept {
+                    rules: host_rules,
+                    guest: GUEST,
+                    first,
+                    clock: state.game.time_control,
+                });
+            }
+            PeerMessage::Accept {
+                rules,
+                guest,
+                first,
+                clock,
+            } if !self.host => {
+                if rules != state.game.rules() {
+                    self.status = format!("host plays {}", rules.describe());
+                    self.conn = None;
+                    return;
+                }
+
+                self.me = guest;
+                self.ready = true;
+                self.status = "game on".to_string();
+                state.game.time_control = clock;
+                state.game.start(first);
+            }
+            PeerMessage::Reject(text) => {
+                self.status = text;
+                self.ready = false;
+                self.conn = None;
+            }
+            PeerMessage::Move { player, mv } if self.host => {
+                let blocked = state.game.game_over() || state.game.offer.is_some();
+                if !self.ready || blocked || player == self.me || player != state.game.turn {
+                    return;
+                }
+
+                if state.game.play(mv) {
+                    self.send(&PeerMessage::Move { player, mv });
+                }
+            }
+            PeerMessage::Move { player, mv } => {
+                state.game.turn = player;
+                state.game.play(mv);
+            }
+            PeerMessage::Rematch if self.host => {
+                if self.ready && state.game.game_over() {
+                    let first = coin_flip(&mut state.rng);
+                    state.game.start(first);
+                    self.send(&PeerMessage::Start { first });
+                }
+            }
+            PeerMessage::Start { first } if !self.host => state.game.start(first),
+            PeerMessage::Resign { player } if player != self.me && !state.game.game_over() => {
+                state.game.offer = None;
+                state.game.outcome = Some(Outcome::Resigned(player));
+            }
+            // the host checks offers and answers from the guest and sends
+            // back the ones it took, the guest takes whatever the host sends
+            msg @ (PeerMessage::OfferDraw { .. }
+            | PeerMessage::RequestTakeback { .. }
+            | PeerMessage::AnswerDraw { .. }
+            | PeerMessage::AnswerTakeback { .. }) => {
+                if !self.host {
+                    confirm(&mut state.game, &msg, None);
+                } else if self.ready && confirm(&mut state.game, &msg, Some(GUEST)) {
+                    self.send(&msg);
+                }
+            }
+            PeerMessage::Chat { text } => state.chat.push(format!("opponent: {}", text)),
+            PeerMessage::Timeout { player } if !self.host => {
+                state.game.offer = None;
+                state.game.outcome = Some(Outcome::Timeout(player));
+            }
+            // only the host answers hellos and rematches, only the guest gets
+            // starts, and stale offers are dropped
+            PeerMessage::Hello { .. }
+            | PeerMessage::Accept { .. }
+            | PeerMessage::Rematch
+            | PeerMessage::Start { .. }
+            | PeerMessage::Timeout { .. }
+            | PeerMessage::Resign { .. } => {}
+        }
+    }
+}
+
+// applies an offer or an answer if it fits the game, returns false if it is
+// stale: an offer while another one waits, or an answer to the wrong one.
+// `sender` is who sent it, none for what the host already checked.
+fn confirm(game: &mut Game, msg: &PeerMessage, sender: Option<Player>) -> bool {
+    if game.game_over() {
+        return false;
+    }
+
+    // players only make their own offers and answer the other one's
+    let offers = |player| sender.is_none_or(|sender| sender == player);
+    let answers = |by| sender != Some(by);
+    match *msg {
+        PeerMessage::OfferDraw { player } if offers(player) && game.offer.is_none() => {
+            game.offer = Some(Offer::Draw(player));
+        }
+        // only the player that moved last can take it back
+        PeerMessage::RequestTakeback { player }
+            if offers(player) && game.offer.is_none() && game.last_mover() == Some(player) =>
+        {
+            game.offer = Some(Offer::Takeback(player));
+        }
+        PeerMessage::AnswerDraw { accept } if matches!(game.offer, Some(Offer::Draw(by)) if answers(by)) =>
+        {
+            game.answer_offer(accept);
+        }
+        PeerMessage::AnswerTakeback { accept } if matches!(game.offer, Some(Offer::Takeback(by)) if answers(by)) =>
+        {
+            game.answer_offer(accept);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+pub fn update(app: &mut App, state: &mut State) {
+    // taken out while it drives the rest of the state
+    let Some(mut lan) = state.lan.take() else {
+        return;
+    };
+
+    lan.update(app, state);
+    state.lan = Some(lan);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Move, Variant};
+
+    #[test]
+    fn only_the_last_mover_takes_back() {
+        let mut game = Game::new(Variant::Classic, None, HOST);
+        let request = PeerMessage::RequestTakeback { player: GUEST };
+        assert!(!confirm(&mut game, &request, Some(GUEST)));
+
+        game.play(Move::Drop(3));
+        // the host's move is not the guest's to take back
+        assert!(!confirm(&mut game, &request, Some(GUEST)));
+        assert_eq!(game.offer, None);
+
+        game.play(Move::Drop(4));
+        assert!(confirm(&mut game, &request, Some(GUEST)));
+        assert_eq!(game.offer, Some(Offer::Takeback(GUEST)));
+
+        // the guest can't answer their own request
+        let answer = PeerMessage::AnswerTakeback { accept: true };
+        assert!(!confirm(&mut game, &answer, Some(GUEST)));
+        assert!(confirm(&mut game, &answer, Some(HOST)));
+        assert_eq!(game.moves, vec![Move::Drop(3)]);
+    }
+
+    #[test]
+    fn requests_in_the_other_players_name_are_dropped() {
+        let mut game = Game::new(Variant::Classic, None, HOST);
+        game.play(Move::Drop(3));
+        let forged = PeerMessage::RequestTakeback { player: HOST };
+        assert!(!confirm(&mut game, &forged, Some(GUEST)));
+        let draw = PeerMessage::OfferDraw { player: HOST };
+        assert!(!confirm(&mut game, &draw, Some(GUEST)));
+        // what the host sends on is taken as it is
+        assert!(confirm(&mut game, &draw, None));
+    }
+}
@@ -0,0 +1,235 @@
+// Screen geometry, worked out from the window size every frame.
+// Everything is placed on an 800 x 760 design, the board area top left, the
+// panel under it and the move list down the right side, which is scaled to
+// fit the window and centred in it.
+// Sizes are in logical pixels, notan maps those to the display's DPI.
+
+use notan::math::{vec2, Vec2};
+
+use crate::board::{Player, Table, Variant};
+use crate::game::Game;
+
+pub const WIDTH: f32 = 600.0;
+pub const HEIGHT: f32 = 600.0;
+pub const MARGIN: f32 = 50.0;
+pub const PANEL_HEIGHT: f32 = 160.0;
+pub const SIDE_WIDTH: f32 = 200.0;
+const FULL_WIDTH: f32 = WIDTH + SIDE_WIDTH;
+
+// share of a cell taken by its disc
+const DISC: f32 = 0.44;
+
+const MENU_ITEM: (f32, f32) = (400.0, 44.0);
+const MENU_TOP: f32 = 160.0;
+const MENU_GAP: f32 = 10.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Layout {
+    pub scale: f32,
+    origin: Vec2,
+    // columns the board is turned by in the cylinder variant, this column is
+    // drawn at the left edge
+    pub turn: usize,
+    // size of the board being played, the cells shrink to fit bigger ones
+    pub cols: usize,
+    pub rows: usize,
+    // off in the free variant, where cells are picked rather than columns
+    pub gravity: bool,
+    // the edges meet in the cylinder variant, so the cursor goes round
+    pub wrap: bool,
+    // a right click pops in PopOut, elsewhere it is free for turning the board
+    pub pops: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new(FULL_WIDTH, HEIGHT + PANEL_HEIGHT)
+    }
+}
+
+impl Layout {
+    pub fn new(width: f32, height: f32) -> Self {
+        let scale = (width / FULL_WIDTH).min(height / (HEIGHT + PANEL_HEIGHT));
+        let origin = vec2(
+            (width - FULL_WIDTH * scale) * 0.5,
+            (height - (HEIGHT + PANEL_HEIGHT) * scale) * 0.5,
+        );
+
+        let table = Table::default();
+        Layout {
+            scale,
+            origin,
+            turn: 0,
+            cols: table.cols(),
+            rows: table.rows(),
+            gravity: true,
+            wrap: false,
+            pops: false,
+        }
+    }
+
+    // the layout for a new window size, keeping the board as it is
+    pub fn resized(self, width: f32, height: f32) -> Self {
+        Layout {
+            turn: self.turn,
+            cols: self.cols,
+            rows: self.rows,
+            gravity: self.gravity,
+            wrap: self.wrap,
+            pops: self.pops,
+            ..Layout::new(width, height)
+        }
+    }
+
+    // follows the board and rules of the game
+    pub fn fit(&mut self, game: &Game) {
+        self.cols = game.table.cols();
+        self.rows = game.table.rows();
+        self.turn %= self.cols;
+        self.gravity = game.variant != Variant::Free;
+        self.wrap = game.variant == Variant::Cylinder;
+        self.pops = game.variant == Variant::PopOut;
+    }
+
+    // a point of the design on the screen
+    pub fn point(&self, x: f32, y: f32) -> Vec2 {
+        self.origin + vec2(x, y) * self.scale
+    }
+
+    // a length or font size of the design on the screen
+    pub fn len(&self, len: f32) -> f32 {
+        len * self.scale
+    }
+
+    // top left corner and size of the area above the panel
+    pub fn screen(&self) -> (Vec2, Vec2) {
+        (self.point(0.0, 0.0), vec2(WIDTH, HEIGHT) * self.scale)
+    }
+
+    // top left corner and size of everything, the panel and move list included
+    pub fn area(&self) -> (Vec2, Vec2) {
+        (
+            self.point(0.0, 0.0),
+            vec2(FULL_WIDTH, HEIGHT + PANEL_HEIGHT) * self.scale,
+        )
+    }
+
+    // top left corner and size of the move list
+    pub fn side(&self) -> (Vec2, Vec2) {
+        (
+            self.point(WIDTH, 0.0),
+            vec2(SIDE_WIDTH, HEIGHT + PANEL_HEIGHT) * self.scale,
+        )
+    }
+
+    // top left corner and size of the board
+    pub fn board(&self) -> (Vec2, Vec2) {
+        let size = vec2(WIDTH - MARGIN * 2.0, HEIGHT - MARGIN * 2.0);
+        (self.point(MARGIN, MARGIN), size * self.scale)
+    }
+
+    pub fn tile(&self) -> Vec2 {
+        let (_, size) = self.board();
+        vec2(size.x / self.cols as f32, size.y / self.rows as f32)
+    }
+
+    // centre of a cell, `row` can be fractional for moving discs
+    pub fn cell_center(&self, col: usize, row: f32) -> Vec2 {
+        let (pos, _) = self.board();
+        let tile = self.tile();
+        let col = (col + self.cols - self.turn) % self.cols;
+        pos + vec2((col as f32 + 0.5) * tile.x, (row + 0.5) * tile.y)
+    }
+
+    // the column drawn `by` places right of the given one, stopping at the
+    // edges unless they wrap
+    pub fn step(&self, col: usize, by: isize) -> usize {
+        let cols = self.cols as isize;
+        let shown = ((col % self.cols + self.cols - self.turn) % self.cols) as isize + by;
+        let shown = if self.wrap {
+            shown.rem_euclid(cols)
+        } else {
+            shown.clamp(0, cols - 1)
+        };
+        (shown as usize + self.turn) % self.cols
+    }
+
+    pub fn disc_radius(&self) -> f32 {
+        let tile = self.tile();
+        tile.x.min(tile.y) * DISC
+    }
+
+    // the column under the given position if it is inside the board
+    pub fn column_at(&self, x: f32, y: f32) -> Option<usize> {
+        let (pos, size) = self.board();
+        if x < pos.x || x >= pos.x + size.x || y < pos.y || y > pos.y + size.y {
+            return None;
+        }
+
+        let col = ((x - pos.x) / self.tile().x).floor() as usize;
+        Some((col + self.turn) % self.cols)
+    }
+
+    // the column and row under the given position if it is inside the board
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let col = self.column_at(x, y)?;
+        let (pos, _) = self.board();
+        let row = ((y - pos.y) / self.tile().y).floor() as usize;
+        Some((col, row.min(self.rows - 1)))
+    }
+
+    // "Playing:" label over the board
+    pub fn turn_label(&self) -> Vec2 {
+        self.point(WIDTH * 0.5, MARGIN * 0.5)
+    }
+
+    pub fn clock(&self, player: Player) -> Vec2 {
+        match player {
+            Player::Red => self.point(MARGIN, MARGIN * 0.5),
+            Player::Yellow => self.point(WIDTH - MARGIN, MARGIN * 0.5),
+            // clocks are only for two players
+            Player::Green | Player::Purple => self.point(WIDTH * 0.5, MARGIN * 0.5),
+        }
+    }
+
+    // line of text under the board
+    pub fn status(&self) -> Vec2 {
+        self.point(WIDTH * 0.5, HEIGHT - MARGIN * 0.5)
+    }
+
+    // match score in the panel, under the buttons
+    pub fn score(&self) -> Vec2 {
+        self.point(WIDTH * 0.5, HEIGHT + 110.0)
+    }
+
+    // a point halfway across everything, for screens that cover it all
+    pub fn middle(&self, y: f32) -> Vec2 {
+        self.point(FULL_WIDTH * 0.5, y)
+    }
+
+    // text on the game over screen, `at` is the share of the height
+    pub fn banner(&self, at: f32) -> Vec2 {
+        self.point(WIDTH * 0.5, HEIGHT * at)
+    }
+
+    pub fn menu_title(&self) -> Vec2 {
+        self.middle(MENU_TOP * 0.5)
+    }
+
+    // top left corner and size of a menu entry
+    pub fn menu_item(&self, index: usize) -> (Vec2, Vec2) {
+        let (width, height) = MENU_ITEM;
+        let y = MENU_TOP + index as f32 * (height + MENU_GAP);
+        (
+            self.point((FULL_WIDTH - width) * 0.5, y),
+            vec2(width, height) * self.scale,
+        )
+    }
+
+    pub fn menu_item_at(&self, x: f32, y: f32, count: usize) -> Option<usize> {
+        (0..count).find(|&i| {
+            let (pos, size) = self.menu_item(i);
+            (pos.x..pos.x + size.x).contains(&x) && (pos.y..pos.y + size.y).contains(&y)
+        })
+    }
+}
@@ -1,338 +1,856 @@
 use notan::draw::*;
 use notan::math::{vec2, Mat3, Vec2};
 use notan::prelude::*;
-use notan::log::debug;
 
-const WIDTH: f32 = 600.0;
-const HEIGHT: f32 = 600.0;
-const MARGIN: f32 = 50.0;
-
-
-#[derive(Default, Copy, Clone, PartialEq, Debug)]
-enum Player {
-    #[default]
-    Red,
-    Yellow,
-}
-
-enum Tied {
-    Yes,
-}
+mod animation;
+mod board;
+#[cfg(not(target_arch = "wasm32"))]
+mod client;
+mod clock;
+mod cylinder;
+mod fog;
+mod game;
+mod history;
+mod input;
+#[cfg(not(target_arch = "wasm32"))]
+mod lan;
+mod layout;
+mod match_play;
+mod menu;
+#[cfg(not(target_arch = "wasm32"))]
+mod online;
+mod options;
+mod panel;
+mod powers;
+mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+mod record;
+mod scene;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+mod settings;
+mod sound;
+mod theme;
+
+use animation::ABOVE;
+use board::{
+    coin_flip, draw_lots, landing_row, Cell, Move, Offer, Outcome, Player, Power, Variant,
+};
+use clock::{format_time, Clock, TimeControl, LOW_TIME};
+use game::Game;
+use input::{Bindings, Command, Input};
+use layout::{Layout, HEIGHT, PANEL_HEIGHT, SIDE_WIDTH, WIDTH};
+use match_play::Match;
+use menu::{List, Mode, Setup};
+use options::Options;
+use panel::{Action, Chat, Controls};
+use scene::{Scene, Scenes};
+use settings::{Settings, Starter};
+use sound::{Effect, Sounds};
+use theme::Style;
 
 #[derive(AppState)]
 struct State {
+    // seeded once, hands out the seed of every game after the first
     rng: Random,
+    next_seed: u64,
     font: Font,
-    turn: Player,
-    table: [[Option<Player>; 7];6],
-    winner: Option<Player>,
-    tie: Option<Tied>,
+    layout: Layout,
+    input: Input,
+    scenes: Scenes,
+    setup: Setup,
+    settings: Settings,
+    sounds: Sounds,
+    game: Game,
+    // ply shown while looking back through the moves, none while live
+    browse: Option<usize>,
+    // the match the hot seat games are part of, if any
+    match_play: Option<Match>,
+    // the power-up a player picked for their next move
+    power: Option<(Player, Power)>,
+    spin: cylinder::Spin,
+    curtain: fog::Curtain,
+    chat: Chat,
+    #[cfg(not(target_arch = "wasm32"))]
+    online: Option<online::Online>,
+    #[cfg(not(target_arch = "wasm32"))]
+    lan: Option<lan::Lan>,
 }
 
 impl State {
-    fn new(gfx: &mut Graphics) -> Self {
+    fn new(app: &mut App, gfx: &mut Graphics, options: &Options) -> Self {
         let font = gfx
             .create_font(include_bytes!("assets/Ubuntu-B.ttf"))
             .unwrap();
 
-        let mut rng = Random::default();
-        let turn = if rng.gen_bool(0.5) {
-            Player::Yellow
-        } else {
-            Player::Red
-        };
+        let mut bindings = Bindings::default();
+        for spec in &options.bind {
+            if let Err(e) = bindings.rebind(spec) {
+                eprintln!("ignoring --bind {}: {}", spec, e);
+            }
+        }
 
-        State {
-            rng,
+        let settings = Settings::load();
+        // a seed is always picked so every game can be played again
+        let seed = options
+            .seed
+            .or(settings.seed)
+            .unwrap_or_else(|| Random::default().gen());
+        let setup = Setup::new(options, &settings);
+        // `--clock none` turns off a clock from the settings
+        let clock = options.clock.unwrap_or(settings.clock);
+
+        let mut state = State {
+            rng: Random::new(seed),
+            next_seed: seed,
             font,
-            turn,
-            table: Default::default(),
-            winner: None,
-            tie: None,
+            layout: Layout::new(gfx.size().0 as f32, gfx.size().1 as f32),
+            input: Input::new(bindings),
+            scenes: Scenes::new(Scene::Menu(List::default())),
+            // stands in until a game is started from the menu
+            game: Game::new(setup.variant, clock, Player::Red),
+            browse: None,
+            match_play: None,
+            power: None,
+            spin: cylinder::Spin::default(),
+            curtain: fog::Curtain::default(),
+            setup,
+            settings,
+            sounds: Sounds::new(&mut app.audio),
+            chat: Chat::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            online: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            lan: None,
+        };
+
+        // a mode given on the command line skips the menu, the saved one is
+        // only where the menu starts
+        if options.picks_mode() {
+            menu::start_game(&mut state);
         }
+
+        state
     }
 
+    // starts the next game with a fresh seed, see `Starter` for who opens it
     fn reset(&mut self) {
-        self.turn = if self.rng.gen_bool(0.5) {
-            Player::Yellow
-        } else {
-            Player::Red
+        let seed = self.next_seed;
+        self.next_seed = self.rng.gen();
+        let mut rng = Random::new(seed);
+
+        // with more than two players the opener goes round, there is no
+        // single loser to go first
+        let players = self.game.players;
+        let last = (!self.game.moves.is_empty()).then_some(self.game.first);
+        let loser = self
+            .game
+            .outcome
+            .and_then(Outcome::winner)
+            .map(Player::other)
+            .filter(|_| players == 2);
+        let first = match self.settings.starter {
+            Starter::Random => None,
+            Starter::Red => Some(Player::Red),
+            Starter::Yellow => Some(Player::Yellow),
+            Starter::Alternate => last.map(|last| last.next(players)),
+            Starter::LoserFirst => loser.or(last.map(|last| last.next(players))),
         };
 
-        self.table = Default::default();
-        self.winner = None;
-        self.tie = None;
+        // matches go their own way after the first game, the first game of
+        // all is a coin flip unless a colour is set
+        let next = self.match_play.as_ref().and_then(Match::next_first);
+        let first = next.or(first).unwrap_or_else(|| match players {
+            2 => coin_flip(&mut rng),
+            _ => draw_lots(&mut rng, players),
+        });
+        self.game.start(first);
+        self.game.seed = Some(seed);
+        // the swap rule is for two players sharing the device
+        let hot_seat = self.setup.mode == Mode::HotSeat;
+        self.game.swap = self.settings.swap && hot_seat && players == 2;
+        self.power = None;
+        self.curtain.reset();
+    }
+
+    // discs fall at the speed from the settings
+    fn animate(&mut self, delta: f32) {
+        let speed = self.settings.animation.speed();
+        if speed > 0.0 {
+            self.game.animate(delta * speed);
+        } else {
+            self.game.settle();
+        }
+    }
+
+    // names from the settings are for players sharing this device, network
+    // games go by colour
+    fn player_name(&self, player: Player) -> String {
+        if controls(self) == Controls::HotSeat {
+            self.settings.name(player).to_string()
+        } else {
+            player.name().to_string()
+        }
     }
 }
 
 pub fn main() -> Result<(), String> {
+    let options = Options::from_args();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(port) = options.server {
+        return server::run(port).map_err(|e| e.to_string());
+    }
+
     let win = WindowConfig::default()
         .set_multisampling(8)
-        .set_size(WIDTH as _, HEIGHT as _)
+        .set_size((WIDTH + SIDE_WIDTH) as _, (HEIGHT + PANEL_HEIGHT) as _)
+        .set_min_size(300, 380)
+        .set_resizable(true)
+        .set_high_dpi(true)
         .set_vsync(true);
 
-    notan::init_with(State::new)
+    // the canvas follows the size of the browser window
+    #[cfg(target_arch = "wasm32")]
+    let win = win.set_maximized(true);
+
+    notan::init_with(move |app: &mut App, gfx: &mut Graphics| State::new(app, gfx, &options))
         .add_config(win)
         .add_config(DrawConfig)
+        .touch_as_mouse(false)
         .update(update)
+        .event(event)
         .draw(draw)
         .build()
 }
 
 fn update(app: &mut App, state: &mut State) {
-    if state.winner.is_some() {
-        if app.keyboard.was_pressed(KeyCode::Space) {
-            state.reset();
+    state.layout.fit(&state.game);
+    let typing = state.chat.is_typing() || state.setup.is_naming();
+    state.input.update(app, typing, &state.layout);
+
+    if matches!(state.scenes.top(), Scene::Game | Scene::Results) {
+        history::update(app, state);
+        cylinder::update(app, state);
+    }
+    if can_play(state) {
+        powers::update(app, state);
+    }
+
+    match state.scenes.top() {
+        Scene::Game => update_game(app, state),
+        Scene::Results => update_results(app, state),
+        Scene::Summary => update_summary(app, state),
+        Scene::Menu(_) | Scene::Pause(_) | Scene::Settings(_) | Scene::Names(_) => {
+            menu::update(app, state)
         }
-        return;
     }
 
-    let x = MARGIN;
-    let y = MARGIN;
-    let width = WIDTH - MARGIN * 2.0;
-    let height = HEIGHT - MARGIN * 2.0;
+    // the results come up once a game ends and go away when the next one starts
+    match state.scenes.top() {
+        Scene::Game if state.game.game_over() => {
+            if let Some(series) = &mut state.match_play {
+                series.finish(&state.game);
+            }
+            state.scenes.push(Scene::Results);
+        }
+        Scene::Results | Scene::Summary if !state.game.game_over() => state.scenes.pop(),
+        _ => {}
+    }
 
-    let tile_width = width / 7.0;
+    sound::update(app, state);
+}
 
-    let (mx, my) = app.mouse.position();
+fn update_game(app: &mut App, state: &mut State) {
+    state.animate(app.timer.delta_f32());
 
-    if app.mouse.was_pressed(MouseButton::Left) {
-        // check bounds
-        if mx < x || mx > x + width {
-            return;
-        }
+    #[cfg(not(target_arch = "wasm32"))]
+    if state.online.is_some() {
+        online::update(app, state);
+        return;
+    }
 
-        if my < y || my > y + height {
+    #[cfg(not(target_arch = "wasm32"))]
+    if state.lan.is_some() {
+        // a LAN game can only be left before it starts or once it is over
+        if state.input.pressed(Command::Back) && !state.game.in_progress() {
+            state.lan = None;
+            state.scenes.back_to_menu();
             return;
         }
 
-        // inside the table
-        let col = ((mx - x) / tile_width).floor();
-        let index = index_from_pos(col as _);
-        // debug!("{} index", index);
+        lan::update(app, state);
+        return;
+    }
 
-        debug!("table cols and rows: {}", state.table.len(), );
-        // set piece
-        let is_empty = matches!(state.table[0][col as usize], None);
-        debug!("is_empty index: {:?}", state.table[0][index]);
-        if !is_empty {
-            return;
-        }
-        
-        let mut row = 0;
+    if state.game.game_over() {
+        return;
+    }
+
+    if state.input.pressed(Command::Back) {
+        state.scenes.push(Scene::Pause(List::default()));
+        return;
+    }
+
+    // the clock can be changed until the first move, games of more than two
+    // are never timed
+    let untimed = state.game.players > 2;
+    if state.game.moves.is_empty() && !untimed && app.keyboard.was_pressed(KeyCode::T) {
+        state.game.time_control = TimeControl::cycle(state.game.time_control);
+        state.game.clock = state.game.time_control.map(Clock::new);
+    }
+
+    if let Some(player) = state.game.tick(app.timer.delta_f32()) {
+        state.game.outcome = Some(Outcome::Timeout(player));
+        return;
+    }
 
-        for i in (0..6).rev() {
-            if state.table[i][col as usize].is_none() {
-                row = i;
-                break;
+    // discs have to land before anyone can act again
+    if state.game.is_animating() {
+        return;
+    }
+
+    // in fog of war the next player has to take the device first
+    if fog::is_down(state) {
+        fog::update(app, state);
+        return;
+    }
+
+    // both players share the device, so the player on turn acts and the
+    // other one answers
+    if let Some((mx, my)) = state.input.clicked(app) {
+        let action = panel::action_at(state, Controls::HotSeat, mx, my);
+        let game = &mut state.game;
+        match action {
+            Some(Action::Resign) => game.resign(game.turn),
+            Some(Action::OfferDraw) => game.offer = Some(Offer::Draw(game.turn)),
+            Some(Action::Takeback) => {
+                let mover = game.last_mover().unwrap_or(game.turn);
+                game.offer = Some(Offer::Takeback(mover));
+            }
+            Some(Action::Accept) => game.answer_offer(true),
+            Some(Action::Decline) => game.answer_offer(false),
+            Some(Action::Swap) => {
+                game.play(Move::Swap);
             }
-        }     
-            state.table[row][col as usize] = Some(state.turn);
+            Some(Action::Keep) => game.keep_colour(),
+            None => {}
+        }
 
-        // change turn
-        state.turn = match state.turn {
-            Player::Red => Player::Yellow,
-            Player::Yellow => Player::Red,
-        };
+        if action.is_some() {
+            return;
+        }
+    }
 
-        // game over
+    // pending offers and the swap have to be answered first
+    if state.game.offer.is_some() || state.game.swap_offered {
+        return;
+    }
 
-        if let Some(winner) = check_winner(&state.table) {
-            state.winner = Some(winner);
+    // the board is read only while looking back
+    if state.browse.is_some() {
+        return;
+    }
 
-        } 
-        if is_full(&state.table) {
-            state.tie = Some(Tied::Yes);
+    if let Some(mv) = state.input.chosen_move(app, &state.layout) {
+        let mv = powers::arm(state, mv);
+        if !state.game.play(mv) {
+            state.sounds.queue(Effect::Invalid);
         }
     }
 }
 
-fn is_full(table: &[[Option<Player>; 7]; 6]) -> bool {
-    for j in 0..=6{//move horizontally
-        if table[0][j] == None { //go through the top row and find if any spot is open
-            return false
+fn update_results(app: &mut App, state: &mut State) {
+    state.animate(app.timer.delta_f32());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if !state.chat.is_typing() && app.keyboard.was_pressed(KeyCode::S) {
+        match state.game.record().save() {
+            Ok(path) => println!("saved the game to {}", path.display()),
+            Err(e) => eprintln!("could not save the game: {}", e),
         }
     }
-    true
-}
 
-fn check_winner(table: &[[Option<Player>; 7];6]) -> Option<Player> {
-    let mut winner: Option<Player> = None;
-    //horizontal check
-    
-    for i in 0..=3{
-        for j in 0..=5{
-            if table[j][i]== table[j][i+1] && table[j][i]== table[j][i+2] && table[j][i]== table[j][i+3] {
-                if table[j][i] != None {
-                    winner = table[j][i]
-                }
-            }
+    // network games handle rematches themselves, ESCAPE leaves the room online
+    #[cfg(not(target_arch = "wasm32"))]
+    if state.online.is_some() {
+        online::update(app, state);
+        return;
+    }
+
+    if state.input.pressed(Command::Back) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            state.lan = None;
         }
+        state.scenes.back_to_menu();
+        return;
     }
 
-    //vertical check
+    #[cfg(not(target_arch = "wasm32"))]
+    if state.lan.is_some() {
+        lan::update(app, state);
+        return;
+    }
 
-    for i in 0..=2{
-        for j in 0..=6{
-            if table[i][j]== table[i+1][j] && table[i][j]== table[i+2][j] && table[i][j]== table[i+3][j] {
-                if table[i][j] != None {
-                    winner = table[i][j]
-                }
-            }
+    if state.input.pressed(Command::Restart) || state.input.tapped() {
+        if state.match_play.as_ref().is_some_and(Match::is_over) {
+            state.scenes.set_top(Scene::Summary);
+        } else {
+            state.reset();
         }
     }
+}
 
-    //ascending diagonal check
+fn update_summary(app: &mut App, state: &mut State) {
+    let Some(series) = &state.match_play else {
+        return;
+    };
 
-    for i in 3..=5{
-        for j in 0..=3{
-            if table[i][j]== table[i-1][j+1] && table[i][j]== table[i-2][j+2] && table[i][j]== table[i-3][j+3] {
-                if table[i][j] != None {
-                    winner = table[i][j]
-                }
-            }
+    #[cfg(target_arch = "wasm32")]
+    let _ = app;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if app.keyboard.was_pressed(KeyCode::S) {
+        match series.save(|player| state.player_name(player)) {
+            Ok(path) => println!("saved the match to {}", path.display()),
+            Err(e) => eprintln!("could not save the match: {}", e),
         }
     }
 
-    //descending diagonal check
+    if state.input.pressed(Command::Back) {
+        state.match_play = None;
+        state.scenes.back_to_menu();
+    } else if state.input.pressed(Command::Restart) || state.input.tapped() {
+        state.match_play = Match::new(series.length, state.settings.match_order);
+        state.reset();
+    }
+}
 
-    for i in 3..=5{
-        for j in 3..=6{
-            if table[i][j]== table[i-1][j-1] && table[i][j]== table[i-2][j-2] && table[i][j]== table[i-3][j-3] {
-                if table[i][j] != None {
-                    winner = table[i][j]
-                }
-            }
+fn event(state: &mut State, event: Event) {
+    let board = (state.scenes.top() == Scene::Game).then_some(&state.layout);
+    state.input.event(&event, board);
+    state.sounds.event(&event);
+    if state.game.variant == Variant::Cylinder {
+        state.spin.event(&event, &mut state.layout);
+    }
+    match event {
+        Event::ReceivedCharacter(c) => {
+            state.chat.on_char(c);
+            state.setup.on_char(c);
+        }
+        Event::WindowResize { width, height } => {
+            state.layout = state.layout.resized(width as f32, height as f32);
         }
+        _ => {}
     }
-    
-    winner
 }
 
-fn draw(gfx: &mut Graphics, state: &mut State) {
+fn controls(state: &State) -> Controls {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(online) = &state.online {
+        return online.controls();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(lan) = &state.lan {
+        return lan.controls();
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = state;
+
+    Controls::HotSeat
+}
+
+// whether a click on the board would drop a disc for the player on turn
+fn can_play(state: &State) -> bool {
+    if state.scenes.top() != Scene::Game || state.browse.is_some() {
+        return false;
+    }
+
+    let game = &state.game;
+    if game.game_over() || game.offer.is_some() || game.swap_offered || game.is_animating() {
+        return false;
+    }
+    if fog::is_down(state) {
+        return false;
+    }
+
+    match controls(state) {
+        Controls::HotSeat => true,
+        Controls::Player(player) => player == state.game.turn,
+        Controls::Watching => false,
+    }
+}
+
+fn draw(app: &mut App, gfx: &mut Graphics, state: &mut State) {
     let mut draw = gfx.create_draw();
-    draw.clear(Color::new(0.68, 0.84, 0.90, 0.1));
+    draw.clear(state.settings.theme.background);
+
+    let top = state.scenes.top();
+    if top == Scene::Game || top.is_overlay() && state.scenes.has_game() {
+        draw_game(app, &mut draw, state);
+    }
+
+    match top {
+        Scene::Game => {}
+        // the results make way for looking back through the game
+        Scene::Results if state.browse.is_some() => {}
+        Scene::Results => draw_results(&mut draw, state),
+        Scene::Summary => {
+            if let Some(series) = &state.match_play {
+                match_play::draw_summary(&mut draw, state, series);
+            }
+        }
+        Scene::Menu(_) | Scene::Pause(_) | Scene::Settings(_) | Scene::Names(_) => {
+            menu::draw(&mut draw, state)
+        }
+    }
 
-    let x = MARGIN;
-    let y = MARGIN;
-    let width = WIDTH - MARGIN * 2.0;
-    let height = HEIGHT - MARGIN * 2.0;
+    gfx.render(&draw);
+}
 
-    let tile_width = width / 7.0;
-    let tile_height = height / 6.0;
+fn draw_game(app: &mut App, draw: &mut Draw, state: &State) {
+    let layout = state.layout;
+    let game = &state.game;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(online) = state.online.as_ref().filter(|online| online.in_lobby()) {
+        online::draw_lobby(draw, &state.font, &layout, online, game.time_control);
+        return;
+    }
 
     // draw "who is playing"
-    let size = vec2(tile_width, tile_height);
-    draw_text(
-        &mut draw,
+    let style = state.settings.style();
+    let radius = layout.disc_radius();
+    let disc = draw_text(
+        draw,
         &state.font,
-        size,
-        state.turn,
+        layout.len(18.0),
+        &style,
+        game.turn,
         "Playing: ",
-        24.0,
-        vec2(300.0, MARGIN * 0.5),
+        layout.len(24.0),
+        layout.turn_label(),
         1.0,
     );
 
+    // with more than two, the ones still in follow smaller in turn order
+    if game.players > 2 && !game.game_over() {
+        let mut playing = game.playing();
+        let at = playing.iter().position(|&p| p == game.turn).unwrap_or(0);
+        playing.rotate_left(at);
+        for (i, &player) in playing.iter().enumerate().skip(1) {
+            let pos = disc + vec2(layout.len(18.0 + 26.0 * i as f32), 0.0);
+            style.draw_disc(draw, player, layout.len(10.0), pos);
+        }
+    }
+    draw_clocks(draw, &state.font, &layout, state);
+
     // drawing board
-    draw.rect((x, y), (width, height))
-        .stroke_color(Color::BLUE)
-        .stroke(6.0);
-
-    for index in 0..7 {
-            draw.line(
-                (x + tile_width * index as f32, y),
-                (x + tile_width * index as f32, y + height),
-            )
-            .width(2.0).color(Color::BLUE);
-            draw.line(
-                (x, y + tile_height * index as f32),
-                (x + width, y + tile_height * index as f32),
-            )
-            .width(2.0).color(Color::BLUE);
-    // }      
-    }
-
-    // // drawing pieces
-    state.table.iter().enumerate().for_each(|(i, p)| {
-        let pos_y = i as f32 * size.y + y *1.8;
-
-        for col_i in 0..7 {
-            let pos_x = col_i as f32 * size.x + x*1.72;
-
-            match p[col_i] {
-                Some(Player::Red) => draw_red(&mut draw, Vec2{x: size.x * 1.75, y: size.y * 1.75}, vec2(pos_x, pos_y)),
-                Some(Player::Yellow) =>  draw_yellow(&mut draw, Vec2{x: size.x * 1.75, y: size.y * 1.75}, vec2(pos_x, pos_y)),
+    let (pos, size) = layout.board();
+    let tile = layout.tile();
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .stroke_color(style.theme.grid)
+        .stroke(layout.len(6.0));
+
+    for col in 1..layout.cols {
+        let x = pos.x + tile.x * col as f32;
+        draw.line((x, pos.y), (x, pos.y + size.y))
+            .width(layout.len(2.0))
+            .color(style.theme.grid);
+    }
+
+    for row in 1..layout.rows {
+        let y = pos.y + tile.y * row as f32;
+        draw.line((pos.x, y), (pos.x + size.x, y))
+            .width(layout.len(2.0))
+            .color(style.theme.grid);
+    }
+
+    // the edge of a turned cylinder, where its first and last columns meet
+    if game.variant == Variant::Cylinder && layout.turn != 0 {
+        let x = pos.x + tile.x * (layout.cols - layout.turn) as f32;
+        draw.line((x, pos.y), (x, pos.y + size.y))
+            .width(layout.len(6.0))
+            .color(style.theme.grid);
+    }
+
+    // drawing pieces, from an earlier move while looking back
+    let table = state
+        .browse
+        .map_or(game.table.clone(), |ply| game.table_at(ply));
+    let falling = game.falling.filter(|_| state.browse.is_none());
+    let popping = game.popping.filter(|_| state.browse.is_none());
+    for (row, cells) in table.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            // the falling disc is drawn on its way down instead
+            if falling.is_some_and(|f| !f.out && f.row == row && f.col == col) {
+                continue;
+            }
+
+            // a popped column is still sliding down into place
+            let lag = popping.filter(|p| p.col == col).map_or(0.0, |p| 1.0 - p.y);
+            let center = layout.cell_center(col, row as f32 - lag);
+            match *cell {
+                Some(Cell::Disc(player)) if fog::hides(state, player) => {
+                    style.draw_hidden(draw, radius, center)
+                }
+                Some(cell) => style.draw_cell(draw, cell, radius, center),
                 None => {}
             }
         }
+    }
+
+    let (mx, my) = app.mouse.position();
+    if !layout.gravity {
+        let hovered = state.input.cursor_cell().or_else(|| layout.cell_at(mx, my));
+        if let Some((col, row)) = hovered.filter(|_| can_play(state)) {
+            draw_placing(draw, &layout, state, col, row);
+        }
+    } else {
+        let hovered = state.input.cursor().or_else(|| layout.column_at(mx, my));
+        if let Some(col) = hovered.filter(|_| can_play(state)) {
+            draw_preview(draw, &state.font, &layout, state, col);
+        }
+    }
+
+    if let Some(falling) = falling {
+        // a wall looks like one on its way down, a disc flying out is gone
+        // from the board already
+        let cell = Some(table[falling.row][falling.col])
+            .filter(|_| !falling.out)
+            .flatten()
+            .unwrap_or(Cell::Disc(falling.player));
+        let center = layout.cell_center(falling.col, falling.y);
+        if fog::hides(state, falling.player) {
+            style.draw_hidden(draw, radius, center);
+        } else {
+            style.draw_cell(draw, cell, radius, center);
+        }
+    }
+
+    if fog::is_down(state) {
+        fog::draw(draw, state);
+    }
+
+    // the popped disc drops out under the board and shrinks away
+    if let Some(popping) = popping {
+        let center = layout.cell_center(popping.col, (layout.rows - 1) as f32 + popping.y);
+        style.draw_disc(draw, popping.player, radius * (1.0 - popping.y), center);
+    }
+
+    let confirm = state.input.is_selected() && can_play(state);
+    let verb = if layout.gravity { "drop" } else { "place" };
+    let status = confirm.then(|| format!("Tap again to {}", verb));
+    let status = status.or_else(|| powers::status(state));
+    let status = status.or_else(|| {
+        state.browse.map(|ply| {
+            let moves = game.moves.len();
+            format!("Move {} of {} - LEFT and RIGHT step", ply, moves)
+        })
     });
+    let status = status.or_else(|| status_line(state)).or_else(|| {
+        game.moves.is_empty().then(|| {
+            let control = clock::control_name(game.time_control);
+            format!("Clock: {} - press T to change", control)
+        })
+    });
+    if let Some(status) = status {
+        let pos = layout.status();
+        draw.text(&state.font, &status)
+            .position(pos.x, pos.y)
+            .size(layout.len(18.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
 
+    panel::draw_panel(
+        draw,
+        &state.font,
+        &layout,
+        state,
+        controls(state),
+        status_line(state).is_some(),
+    );
+    history::draw(draw, state);
+    powers::draw(draw, state);
 
+    if let Some(series) = &state.match_play {
+        match_play::draw_score(draw, state, series);
+    }
+}
 
-    // draw final menu
-    if let Some(winner) = state.winner {
-        draw.rect((0.0, 0.0), (WIDTH, HEIGHT))
-            .color(Color::GRAY)
-            .alpha(0.8);
+// who won and how over the finished game
+fn draw_results(draw: &mut Draw, state: &State) {
+    let layout = state.layout;
+    let game = &state.game;
 
-        let (text, x_offet) = ("Winner: ", size.x * 0.3);
+    let (pos, size) = layout.screen();
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .color(Color::GRAY)
+        .alpha(0.8);
 
+    if let Some(winner) = game.outcome.and_then(|outcome| outcome.winner()) {
+        let pos = layout.banner(0.5) - vec2(layout.tile().x * 0.3, 0.0);
         draw_text(
-            &mut draw,
+            draw,
             &state.font,
-            size,
+            layout.len(18.0),
+            &state.settings.style(),
             winner,
-            text,
-            48.0,
-            vec2(WIDTH * 0.5 - x_offet, HEIGHT * 0.5),
+            "Winner: ",
+            layout.len(48.0),
+            pos,
             0.6,
         );
-
-        draw.text(&state.font, "Press SPACE to reset")
-            .position(WIDTH * 0.5, HEIGHT * 0.75)
-            .size(32.0)
+    } else {
+        // a full misère board is a draw too, nobody was forced into a line
+        let drawn = game.variant == Variant::Misere
+            || matches!(
+                game.outcome,
+                Some(Outcome::AgreedDraw | Outcome::Repetition)
+            );
+        let text = if drawn { "Draw" } else { "Tie" };
+
+        let pos = layout.banner(0.5);
+        draw.text(&state.font, text)
+            .position(pos.x, pos.y)
+            .size(layout.len(48.0))
             .h_align_center()
             .v_align_middle()
             .color(Color::BLACK);
     }
 
-    if let Some(_tie) = &state.tie {
-        draw.rect((0.0, 0.0), (WIDTH, HEIGHT))
-            .color(Color::GRAY)
-            .alpha(0.8);
-
-
-        draw.text(&state.font, "Tie")
-            .position(WIDTH * 0.5, HEIGHT * 0.5)
-            .size(48.0)
+    let reason = game
+        .outcome
+        .and_then(|outcome| outcome.reason(|player| state.player_name(player)));
+    if let Some(reason) = reason {
+        let pos = layout.banner(0.62);
+        draw.text(&state.font, &reason)
+            .position(pos.x, pos.y)
+            .size(layout.len(24.0))
             .h_align_center()
             .v_align_middle()
             .color(Color::BLACK);
+    }
 
+    let pos = layout.banner(0.75);
+    draw.text(&state.font, end_hint(state))
+        .position(pos.x, pos.y)
+        .size(layout.len(32.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let hint = "S saves the game, ESCAPE leaves";
+    #[cfg(target_arch = "wasm32")]
+    let hint = "ESCAPE for the main menu";
+
+    let pos = layout.banner(0.85);
+    draw.text(&state.font, hint)
+        .position(pos.x, pos.y)
+        .size(layout.len(20.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+}
 
-        draw.text(&state.font, "Press SPACE to reset")
-            .position(WIDTH * 0.5, HEIGHT * 0.75)
-            .size(32.0)
+// translucent disc above the hovered column and a ring in the cell it would
+// land in, or a note that the column is full
+fn draw_preview(draw: &mut Draw, font: &Font, layout: &Layout, state: &State, col: usize) {
+    let above = layout.cell_center(col, ABOVE);
+    let Some(row) = landing_row(&state.game.table, col) else {
+        draw.text(font, "full")
+            .position(above.x, above.y)
+            .size(layout.len(18.0))
             .h_align_center()
             .v_align_middle()
             .color(Color::BLACK);
+        return;
+    };
+
+    let color = state.settings.style().color(state.game.turn);
+    let radius = layout.disc_radius();
+
+    draw.circle(radius)
+        .position(above.x, above.y)
+        .fill_color(color)
+        .alpha(0.5)
+        .fill();
+
+    let landing = layout.cell_center(col, row as f32);
+    draw.circle(radius)
+        .position(landing.x, landing.y)
+        .stroke_color(color)
+        .stroke(layout.len(4.0));
+}
+
+// with gravity off, a translucent disc in the hovered cell if it is free
+fn draw_placing(draw: &mut Draw, layout: &Layout, state: &State, col: usize, row: usize) {
+    // the cursor may still be on a bigger board from before
+    if state.game.table.get(row, col) != Some(None) {
+        return;
     }
 
-    gfx.render(&draw);
+    let color = state.settings.style().color(state.game.turn);
+    let center = layout.cell_center(col, row as f32);
+    draw.circle(layout.disc_radius())
+        .position(center.x, center.y)
+        .fill_color(color)
+        .alpha(0.5)
+        .fill();
+}
+
+// both clocks beside the "Playing:" label, the running one stands out
+fn draw_clocks(draw: &mut Draw, font: &Font, layout: &Layout, state: &State) {
+    let Some(clock) = &state.game.clock else {
+        return;
+    };
+
+    for player in [Player::Red, Player::Yellow] {
+        let left = clock.left(player);
+        let color = if left < LOW_TIME {
+            Color::RED
+        } else {
+            Color::BLACK
+        };
+        let alpha = if player == state.game.turn && !state.game.game_over() {
+            1.0
+        } else {
+            0.4
+        };
+
+        let pos = layout.clock(player);
+        let text = format!("{} {}", state.player_name(player), format_time(left));
+        let mut text = draw.text(font, &text);
+        text.position(pos.x, pos.y)
+            .size(layout.len(20.0))
+            .v_align_middle()
+            .color(color)
+            .alpha(alpha);
+        match player {
+            Player::Red => text.h_align_left(),
+            _ => text.h_align_right(),
+        };
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
 fn draw_text(
     draw: &mut Draw,
     font: &Font,
-    size: Vec2,
+    radius: f32,
+    style: &Style,
     player: Player,
     text: &str,
     font_size: f32,
     pos: Vec2,
     scale: f32,
-) {
+) -> Vec2 {
     // drawing text
     draw.text(font, text)
         .color(Color::BLACK)
@@ -343,34 +861,51 @@ fn draw_text(
 
     let bounds = draw.last_text_bounds();
 
-    let pos = vec2(bounds.max_x() + 30.0, bounds.center_y());
+    // leave about a radius of space after the text
+    let pos = vec2(bounds.max_x() + radius * 1.7, bounds.center_y());
     let mm = Mat3::from_translation(pos)
         * Mat3::from_scale(Vec2::splat(scale))
         * Mat3::from_translation(-pos);
     draw.transform().push(mm);
-    match player {
-        Player::Red => draw_red(draw, size, pos),
-        Player::Yellow => draw_yellow(draw, size, pos),
-    }
+    style.draw_disc(draw, player, radius, pos);
     draw.transform().pop();
-}
 
-fn draw_red(draw: &mut Draw, size: Vec2, pos: Vec2) {
-    let radius = size.x/4.0 ;
-    draw.circle(radius)
-        .position(pos.x, pos.y)
-        .fill_color(Color::RED)
-        .fill();
+    // where the disc went
+    pos
 }
 
-fn draw_yellow(draw: &mut Draw, size: Vec2, pos: Vec2) {
-    let radius = size.x/4.0;
-    draw.circle(radius)
-        .position(pos.x, pos.y)
-        .fill_color(Color::YELLOW)
-        .fill();
+// network games show who we are and what's going on under the board
+fn status_line(state: &State) -> Option<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(online) = &state.online {
+        return Some(online.status());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(lan) = &state.lan {
+        return Some(lan.status());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = state;
+
+    None
 }
 
-fn index_from_pos(x: usize) -> usize {
-    x
-}
\ No newline at end of file
+fn end_hint(state: &State) -> &'static str {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(online) = &state.online {
+        return online.end_hint();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(lan) = &state.lan {
+        return lan.end_hint();
+    }
+
+    if state.match_play.as_ref().is_some_and(Match::is_over) {
+        return "Press SPACE or tap for the summary";
+    }
+
+    "Press SPACE or tap to reset"
+}
@@ -0,0 +1,280 @@
+// Matches between two players sharing the device, best of N games or first to
+// N wins. The score runs across the games of the match; the first game is
+// opened as the settings say, the later ones in turn or by the loser of the
+// game before. Saved matches hold the score and every game record, e.g.
+//
+//   match best of 3
+//   red Alice
+//   yellow Bob
+//   score 2 1 0
+//   winner red
+//
+//   variant classic
+//   ...
+//
+// The score counts red's wins, yellow's wins and draws.
+
+use notan::draw::*;
+use notan::prelude::*;
+
+use crate::board::{Outcome, Player};
+use crate::game::Game;
+use crate::State;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Length {
+    Single,
+    BestOf(u32),
+    FirstTo(u32),
+}
+
+const LENGTHS: [Length; 6] = [
+    Length::Single,
+    Length::BestOf(3),
+    Length::BestOf(5),
+    Length::BestOf(7),
+    Length::FirstTo(3),
+    Length::FirstTo(5),
+];
+
+impl Length {
+    pub fn name(self) -> String {
+        match self {
+            Length::Single => "single game".to_string(),
+            Length::BestOf(games) => format!("best of {}", games),
+            Length::FirstTo(wins) => format!("first to {}", wins),
+        }
+    }
+
+    pub fn next(self) -> Length {
+        let i = LENGTHS.iter().position(|&length| length == self);
+        LENGTHS[i.map_or(0, |i| (i + 1) % LENGTHS.len())]
+    }
+}
+
+// who opens the games after the first one
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Order {
+    Alternate,
+    LoserStarts,
+}
+
+impl Order {
+    const ALL: [Order; 2] = [Order::Alternate, Order::LoserStarts];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Order::Alternate => "alternate",
+            Order::LoserStarts => "loser starts",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Order> {
+        Order::ALL.into_iter().find(|order| order.name() == name)
+    }
+
+    pub fn next(self) -> Order {
+        match self {
+            Order::Alternate => Order::LoserStarts,
+            Order::LoserStarts => Order::Alternate,
+        }
+    }
+}
+
+pub struct Match {
+    pub length: Length,
+    pub order: Order,
+    // who opened each finished game and how it ended
+    pub games: Vec<(Player, Outcome)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    records: Vec<crate::record::Record>,
+}
+
+impl Match {
+    // none for a single game
+    pub fn new(length: Length, order: Order) -> Option<Match> {
+        (length != Length::Single).then(|| Match {
+            length,
+            order,
+            games: vec![],
+            #[cfg(not(target_arch = "wasm32"))]
+            records: vec![],
+        })
+    }
+
+    // counts the finished game towards the score
+    pub fn finish(&mut self, game: &Game) {
+        let Some(outcome) = game.outcome else {
+            return;
+        };
+
+        self.games.push((game.first, outcome));
+        #[cfg(not(target_arch = "wasm32"))]
+        self.records.push(game.record());
+    }
+
+    pub fn wins(&self, player: Player) -> u32 {
+        let won = |(_, outcome): &&(Player, Outcome)| outcome.winner() == Some(player);
+        self.games.iter().filter(won).count() as u32
+    }
+
+    pub fn draws(&self) -> u32 {
+        let drawn = |(_, outcome): &&(Player, Outcome)| outcome.winner().is_none();
+        self.games.iter().filter(drawn).count() as u32
+    }
+
+    pub fn is_over(&self) -> bool {
+        let most = self.wins(Player::Red).max(self.wins(Player::Yellow));
+        match self.length {
+            Length::Single => !self.games.is_empty(),
+            // drawn games count as played, so the match can end level
+            Length::BestOf(games) => most > games / 2 || self.games.len() as u32 >= games,
+            Length::FirstTo(wins) => most >= wins,
+        }
+    }
+
+    // whoever is ahead once the match is over
+    pub fn winner(&self) -> Option<Player> {
+        if !self.is_over() {
+            return None;
+        }
+
+        let (red, yellow) = (self.wins(Player::Red), self.wins(Player::Yellow));
+        match red.cmp(&yellow) {
+            std::cmp::Ordering::Greater => Some(Player::Red),
+            std::cmp::Ordering::Less => Some(Player::Yellow),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    // who opens the next game, none before the first one
+    pub fn next_first(&self) -> Option<Player> {
+        let &(first, outcome) = self.games.last()?;
+        match (self.order, outcome.winner()) {
+            (Order::LoserStarts, Some(winner)) => Some(winner.other()),
+            // a drawn game has no loser, so the other player opens
+            _ => Some(first.other()),
+        }
+    }
+
+    // writes the match to a new file in `games/` and returns its path,
+    // `name` gives the name of each player
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, name: impl Fn(Player) -> String) -> std::io::Result<std::path::PathBuf> {
+        let winner = self.winner().map_or("draw", |winner| winner.name());
+        let mut text = format!(
+            "match {}\nred {}\nyellow {}\nscore {} {} {}\nwinner {}\n",
+            self.length.name(),
+            name(Player::Red),
+            name(Player::Yellow),
+            self.wins(Player::Red),
+            self.wins(Player::Yellow),
+            self.draws(),
+            winner
+        );
+        for record in &self.records {
+            text.push('\n');
+            text.push_str(&record.encode());
+        }
+
+        crate::record::save_file("match", &text)
+    }
+}
+
+// the running score in the panel under the board
+pub fn draw_score(draw: &mut Draw, state: &State, series: &Match) {
+    let layout = &state.layout;
+    let mut text = format!(
+        "{} {} - {} {}, {}",
+        state.player_name(Player::Red),
+        series.wins(Player::Red),
+        series.wins(Player::Yellow),
+        state.player_name(Player::Yellow),
+        series.length.name()
+    );
+    if series.draws() > 0 {
+        text.push_str(&format!(", {} drawn", series.draws()));
+    }
+
+    let pos = layout.score();
+    draw.text(&state.font, &text)
+        .position(pos.x, pos.y)
+        .size(layout.len(20.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+}
+
+// lines of the game list on the summary that fit the screen
+const SUMMARY_GAMES: usize = 10;
+
+// the final score and how every game of the match went
+pub fn draw_summary(draw: &mut Draw, state: &State, series: &Match) {
+    let layout = &state.layout;
+    let font = &state.font;
+
+    let (pos, size) = layout.area();
+    draw.rect((pos.x, pos.y), (size.x, size.y))
+        .color(Color::GRAY)
+        .alpha(0.9);
+
+    let pos = layout.menu_title();
+    draw.text(font, "Match over")
+        .position(pos.x, pos.y)
+        .size(layout.len(56.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    let (red, yellow) = (series.wins(Player::Red), series.wins(Player::Yellow));
+    let result = match series.winner() {
+        Some(winner) => format!(
+            "{} wins the match {} - {}",
+            state.player_name(winner),
+            red.max(yellow),
+            red.min(yellow)
+        ),
+        None => format!("The match is drawn {} - {}", red, yellow),
+    };
+    let pos = layout.middle(190.0);
+    draw.text(font, &result)
+        .position(pos.x, pos.y)
+        .size(layout.len(32.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    let first = series.games.len().saturating_sub(SUMMARY_GAMES);
+    for (i, &(_, outcome)) in series.games.iter().enumerate().skip(first) {
+        let result = match outcome.winner() {
+            Some(winner) => format!("{} won", state.player_name(winner)),
+            None => "drawn".to_string(),
+        };
+        let reason = outcome.reason(|player| state.player_name(player));
+        let line = match reason {
+            Some(reason) => format!("Game {}: {}, {}", i + 1, result, reason),
+            None => format!("Game {}: {}", i + 1, result),
+        };
+
+        let pos = layout.middle(250.0 + (i - first) as f32 * 32.0);
+        draw.text(font, &line)
+            .position(pos.x, pos.y)
+            .size(layout.len(20.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let hint = "SPACE plays a new match, S saves this one, ESCAPE leaves";
+    #[cfg(target_arch = "wasm32")]
+    let hint = "SPACE or tap plays a new match, ESCAPE leaves";
+
+    let pos = layout.middle(640.0);
+    draw.text(font, hint)
+        .position(pos.x, pos.y)
+        .size(layout.len(20.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+}
@@ -0,0 +1,620 @@
+// The main menu, the pause menu and the settings. Each is a list of entries
+// picked with UP, DOWN and ENTER, the mouse or a tap; entries with a value
+// show it after a colon and step to the next value when picked.
+
+use notan::draw::*;
+use notan::prelude::*;
+
+use crate::board::{Player, Table, Variant, CONNECT};
+use crate::clock::{control_name, Clock, TimeControl};
+use crate::input::{Command, Input};
+use crate::layout::Layout;
+use crate::match_play::{Length, Match};
+use crate::options::Options;
+use crate::panel::Chat;
+use crate::scene::Scene;
+use crate::settings::{color_name, Settings};
+use crate::theme::{disc_color_name, next_disc_color, CUSTOM};
+use crate::State;
+
+// who the next game is played against
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Mode {
+    HotSeat,
+    #[cfg(not(target_arch = "wasm32"))]
+    Online,
+    #[cfg(not(target_arch = "wasm32"))]
+    HostLan,
+    #[cfg(not(target_arch = "wasm32"))]
+    JoinLan,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const MODES: &[Mode] = &[Mode::HotSeat, Mode::Online, Mode::HostLan, Mode::JoinLan];
+#[cfg(target_arch = "wasm32")]
+const MODES: &[Mode] = &[Mode::HotSeat];
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::HotSeat => "hot seat",
+            #[cfg(not(target_arch = "wasm32"))]
+            Mode::Online => "online",
+            #[cfg(not(target_arch = "wasm32"))]
+            Mode::HostLan => "host LAN",
+            #[cfg(not(target_arch = "wasm32"))]
+            Mode::JoinLan => "join LAN",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mode> {
+        MODES.iter().copied().find(|mode| mode.name() == name)
+    }
+
+    fn next(self) -> Mode {
+        let i = MODES.iter().position(|&mode| mode == self).unwrap_or(0);
+        MODES[(i + 1) % MODES.len()]
+    }
+}
+
+// boards to pick from for the free variant, columns and rows
+const BOARDS: [(usize, usize); 4] = [(7, 6), (9, 9), (11, 11), (15, 15)];
+
+// longest name that can be typed in the settings
+const NAME_LENGTH: usize = 16;
+
+// what "New game" starts, the addresses come from the command line and the
+// mode from the settings unless the command line picks one
+pub struct Setup {
+    pub mode: Mode,
+    pub variant: Variant,
+    // hot seat games can have up to four players, and lines of three to six
+    // that can be made exact, so longer ones don't count
+    pub players: usize,
+    pub connect: usize,
+    pub exact: bool,
+    // picked from `BOARDS` for hot seat games of the free variant
+    pub size: usize,
+    // hot seat games of two can be played as a match
+    pub length: Length,
+    #[cfg(not(target_arch = "wasm32"))]
+    server: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    name: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    port: u16,
+    #[cfg(not(target_arch = "wasm32"))]
+    peer: String,
+    // why the last game could not be started
+    error: Option<String>,
+    // the name being typed in the settings and whose it is
+    naming: Option<(Player, String)>,
+}
+
+impl Setup {
+    pub fn new(options: &Options, settings: &Settings) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let mode = if options.connect.is_some() {
+            Mode::Online
+        } else if options.host.is_some() {
+            Mode::HostLan
+        } else if options.join.is_some() {
+            Mode::JoinLan
+        } else {
+            settings.opponent
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        let (mode, _) = (settings.opponent, options);
+
+        Setup {
+            mode,
+            variant: Variant::default(),
+            players: 2,
+            connect: CONNECT,
+            exact: false,
+            size: 1,
+            length: Length::Single,
+            #[cfg(not(target_arch = "wasm32"))]
+            server: options
+                .connect
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+            #[cfg(not(target_arch = "wasm32"))]
+            name: options.name.clone().unwrap_or_else(|| "player".to_string()),
+            #[cfg(not(target_arch = "wasm32"))]
+            port: options.host.unwrap_or(crate::protocol::DEFAULT_PORT),
+            #[cfg(not(target_arch = "wasm32"))]
+            peer: options
+                .join
+                .clone()
+                .unwrap_or_else(|| "127.0.0.1".to_string()),
+            error: None,
+            naming: None,
+        }
+    }
+
+    pub fn is_naming(&self) -> bool {
+        self.naming.is_some()
+    }
+
+    pub fn on_char(&mut self, c: char) {
+        if let Some((_, name)) = &mut self.naming {
+            if !c.is_control() && name.chars().count() < NAME_LENGTH {
+                name.push(c);
+            }
+        }
+    }
+
+    // network games are for two on the classic board, and misère only works
+    // out with two
+    fn can_grow(&self) -> bool {
+        self.mode == Mode::HotSeat && self.variant != Variant::Misere
+    }
+
+    // players, line length and whether it is exact for the next game
+    pub fn board(&self) -> (usize, usize, bool) {
+        if self.can_grow() {
+            (self.players, self.connect, self.exact)
+        } else {
+            (2, CONNECT, false)
+        }
+    }
+
+    fn picks_size(&self) -> bool {
+        self.mode == Mode::HotSeat && self.variant == Variant::Free
+    }
+
+    // the empty board of the next game
+    pub fn table(&self) -> Table {
+        if self.picks_size() {
+            let (cols, rows) = BOARDS[self.size];
+            Table::new(cols, rows)
+        } else {
+            Table::for_players(self.board().0)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct List {
+    selected: usize,
+}
+
+impl List {
+    // moves the selection and returns the entry picked this frame
+    fn update(&mut self, app: &App, input: &Input, layout: &Layout, count: usize) -> Option<usize> {
+        if input.pressed(Command::Up) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.pressed(Command::Down) {
+            self.selected = (self.selected + 1) % count;
+        }
+        self.selected = self.selected.min(count - 1);
+
+        if app.mouse.is_moving() {
+            let (mx, my) = app.mouse.position();
+            if let Some(i) = layout.menu_item_at(mx, my, count) {
+                self.selected = i;
+            }
+        }
+
+        if let Some((x, y)) = input.clicked(app) {
+            let i = layout.menu_item_at(x, y, count)?;
+            self.selected = i;
+            return Some(i);
+        }
+
+        input.pressed(Command::Select).then_some(self.selected)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Entry {
+    Continue,
+    NewGame,
+    Mode,
+    Variant,
+    Players,
+    Connect,
+    Board,
+    Match,
+    Settings,
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+    Resume,
+    MainMenu,
+    Clock,
+    Theme,
+    Pattern,
+    Animation,
+    Volume,
+    Sound,
+    Starter,
+    Swap,
+    MatchOrder,
+    Names,
+    Name(Player),
+    RedColor,
+    YellowColor,
+    Back,
+}
+
+impl Entry {
+    fn label(self, state: &State) -> String {
+        match self {
+            Entry::Continue => "Continue".to_string(),
+            Entry::NewGame => "New game".to_string(),
+            Entry::Mode => format!("Mode: {}", state.setup.mode.name()),
+            Entry::Variant => format!("Variant: {}", state.setup.variant.name()),
+            Entry::Players => format!("Players: {}", state.setup.players),
+            Entry::Connect if state.setup.exact => format!("Line: exactly {}", state.setup.connect),
+            Entry::Connect => format!("Line: {} in a row", state.setup.connect),
+            Entry::Board => {
+                let (cols, rows) = BOARDS[state.setup.size];
+                format!("Board: {} x {}", cols, rows)
+            }
+            Entry::Match => format!("Match: {}", state.setup.length.name()),
+            Entry::Settings => "Settings".to_string(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Entry::Quit => "Quit".to_string(),
+            Entry::Resume => "Resume".to_string(),
+            Entry::MainMenu => "Main menu".to_string(),
+            Entry::Clock => format!("Clock: {}", control_name(state.game.time_control)),
+            Entry::Theme => format!("Theme: {}", state.settings.theme.name),
+            Entry::Pattern => format!("Discs: {}", state.settings.pattern.name()),
+            Entry::Animation => format!("Animation: {}", state.settings.animation.name()),
+            Entry::Volume => format!("Volume: {}%", state.settings.volume),
+            Entry::Sound => format!("Sound: {}", if state.settings.muted { "off" } else { "on" }),
+            Entry::Starter => format!("First move: {}", state.settings.starter.name()),
+            Entry::Swap => format!(
+                "Swap rule: {}",
+                if state.settings.swap { "on" } else { "off" }
+            ),
+            Entry::MatchOrder => format!("Match openers: {}", state.settings.match_order.name()),
+            Entry::Names => "Names and colours".to_string(),
+            // the cursor shows while typing
+            Entry::Name(player) => match &state.setup.naming {
+                Some((typing, name)) if *typing == player => {
+                    format!("Name of {}: {}_", player.name(), name)
+                }
+                _ => format!("Name of {}: {}", player.name(), state.settings.name(player)),
+            },
+            Entry::RedColor => color_label(Player::Red, state.settings.red_color),
+            Entry::YellowColor => color_label(Player::Yellow, state.settings.yellow_color),
+            Entry::Back => "Back".to_string(),
+        }
+    }
+}
+
+// colours set by hand in the file show as they are written there
+fn color_label(player: Player, color: Color) -> String {
+    let name = disc_color_name(color).map_or_else(|| color_name(color), str::to_string);
+    format!("Colour of {}: {}", player.name(), name)
+}
+
+fn entries(state: &State, scene: Scene) -> (&'static str, List, Vec<Entry>) {
+    match scene {
+        Scene::Pause(list) => (
+            "Paused",
+            list,
+            vec![
+                Entry::Resume,
+                Entry::NewGame,
+                Entry::Settings,
+                Entry::MainMenu,
+            ],
+        ),
+        Scene::Settings(list) => (
+            "Settings",
+            list,
+            vec![
+                Entry::Clock,
+                Entry::Theme,
+                Entry::Pattern,
+                Entry::Animation,
+                Entry::Volume,
+                Entry::Sound,
+                Entry::Starter,
+                Entry::Swap,
+                Entry::MatchOrder,
+                Entry::Names,
+                Entry::Back,
+            ],
+        ),
+        Scene::Names(list) => (
+            "Names and colours",
+            list,
+            vec![
+                Entry::Name(Player::Red),
+                Entry::Name(Player::Yellow),
+                Entry::Name(Player::Green),
+                Entry::Name(Player::Purple),
+                Entry::RedColor,
+                Entry::YellowColor,
+                Entry::Back,
+            ],
+        ),
+        Scene::Menu(list) => {
+            // games left from the pause menu can be picked up again
+            let mut entries = vec![];
+            if state.game.in_progress() && state.setup.mode == Mode::HotSeat {
+                entries.push(Entry::Continue);
+            }
+            entries.extend([Entry::NewGame, Entry::Mode, Entry::Variant]);
+            if state.setup.can_grow() {
+                entries.extend([Entry::Players, Entry::Connect]);
+            }
+            if state.setup.picks_size() {
+                entries.push(Entry::Board);
+            }
+            if state.setup.mode == Mode::HotSeat && state.setup.board().0 == 2 {
+                entries.push(Entry::Match);
+            }
+            entries.push(Entry::Settings);
+            #[cfg(not(target_arch = "wasm32"))]
+            entries.push(Entry::Quit);
+
+            ("Connect 4", list, entries)
+        }
+        Scene::Game | Scene::Results | Scene::Summary => ("", List::default(), vec![]),
+    }
+}
+
+fn with_list(scene: Scene, list: List) -> Scene {
+    match scene {
+        Scene::Menu(_) => Scene::Menu(list),
+        Scene::Pause(_) => Scene::Pause(list),
+        Scene::Settings(_) => Scene::Settings(list),
+        Scene::Names(_) => Scene::Names(list),
+        scene => scene,
+    }
+}
+
+pub fn update(app: &mut App, state: &mut State) {
+    if state.setup.naming.is_some() {
+        update_naming(app, state);
+        return;
+    }
+
+    let scene = state.scenes.top();
+    let (_, mut list, entries) = entries(state, scene);
+    if entries.is_empty() {
+        return;
+    }
+
+    let picked = list.update(app, &state.input, &state.layout, entries.len());
+    state.scenes.set_top(with_list(scene, list));
+
+    if state.input.pressed(Command::Back) && !matches!(scene, Scene::Menu(_)) {
+        state.scenes.pop();
+        return;
+    }
+
+    let Some(entry) = picked.map(|i| entries[i]) else {
+        return;
+    };
+
+    match entry {
+        Entry::Continue => state.scenes.push(Scene::Game),
+        Entry::NewGame => start_game(state),
+        Entry::Mode => {
+            state.setup.mode = state.setup.mode.next();
+            state.settings.opponent = state.setup.mode;
+            state.settings.save();
+        }
+        Entry::Variant => state.setup.variant = state.setup.variant.next(),
+        Entry::Players => state.setup.players = (state.setup.players - 1) % 3 + 2,
+        Entry::Connect => {
+            // three to six in a row, then the same exactly
+            let setup = &mut state.setup;
+            if setup.connect == 6 {
+                setup.exact = !setup.exact;
+            }
+            setup.connect = (setup.connect - 2) % 4 + 3;
+        }
+        Entry::Board => state.setup.size = (state.setup.size + 1) % BOARDS.len(),
+        Entry::Match => state.setup.length = state.setup.length.next(),
+        Entry::Settings => state.scenes.push(Scene::Settings(List::default())),
+        #[cfg(not(target_arch = "wasm32"))]
+        Entry::Quit => app.exit(),
+        Entry::Resume | Entry::Back => state.scenes.pop(),
+        Entry::MainMenu => state.scenes.back_to_menu(),
+        Entry::Clock => {
+            let game = &mut state.game;
+            game.time_control = TimeControl::cycle(game.time_control);
+            // a running clock keeps going until the next game
+            if game.moves.is_empty() && game.players == 2 {
+                game.clock = game.time_control.map(Clock::new);
+            }
+            state.settings.clock = game.time_control;
+            state.settings.save();
+        }
+        Entry::Theme => {
+            state.settings.theme = state.settings.theme.next();
+            state.settings.save();
+        }
+        Entry::Pattern => {
+            state.settings.pattern = state.settings.pattern.next();
+            state.settings.save();
+        }
+        Entry::Animation => {
+            state.settings.animation = state.settings.animation.next();
+            state.settings.save();
+        }
+        Entry::Volume => {
+            // steps of ten, from full back to the quietest
+            state.settings.volume = (state.settings.volume / 10 % 10 + 1) * 10;
+            state.settings.save();
+        }
+        Entry::Sound => {
+            state.settings.muted = !state.settings.muted;
+            state.settings.save();
+        }
+        Entry::Starter => {
+            state.settings.starter = state.settings.starter.next();
+            state.settings.save();
+        }
+        Entry::Swap => {
+            state.settings.swap = !state.settings.swap;
+            state.settings.save();
+        }
+        Entry::MatchOrder => {
+            state.settings.match_order = state.settings.match_order.next();
+            state.settings.save();
+        }
+        Entry::Names => state.scenes.push(Scene::Names(List::default())),
+        Entry::Name(player) => state.setup.naming = Some((player, String::new())),
+        // the picked colours are only used by the custom theme, so it comes on
+        Entry::RedColor => {
+            state.settings.red_color = next_disc_color(state.settings.red_color);
+            state.settings.theme = CUSTOM;
+            state.settings.save();
+        }
+        Entry::YellowColor => {
+            state.settings.yellow_color = next_disc_color(state.settings.yellow_color);
+            state.settings.theme = CUSTOM;
+            state.settings.save();
+        }
+    }
+}
+
+// ENTER keeps the typed name, ESCAPE or an empty one leaves the old name
+fn update_naming(app: &App, state: &mut State) {
+    let keyboard = &app.keyboard;
+    if keyboard.was_pressed(KeyCode::Escape) {
+        state.setup.naming = None;
+    } else if keyboard.was_pressed(KeyCode::Back) {
+        if let Some((_, name)) = &mut state.setup.naming {
+            name.pop();
+        }
+    } else if keyboard.was_pressed(KeyCode::Return) {
+        let Some((player, name)) = state.setup.naming.take() else {
+            return;
+        };
+        let name = name.trim();
+        if !name.is_empty() {
+            state.settings.set_name(player, name.to_string());
+            state.settings.save();
+        }
+    }
+}
+
+// starts a game in the picked mode, the menu shows why if that fails
+pub fn start_game(state: &mut State) {
+    let started = new_game(state);
+    // the board may have changed size, the layout has to follow before it is drawn
+    state.layout.fit(&state.game);
+    match started {
+        Ok(()) => {
+            state.setup.error = None;
+            state.scenes.back_to_menu();
+            state.scenes.push(Scene::Game);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            state.setup.error = Some(e);
+        }
+    }
+}
+
+fn new_game(state: &mut State) -> Result<(), String> {
+    // whatever ran before is left behind
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        state.online = None;
+        state.lan = None;
+    }
+    state.chat = Chat::default();
+    state.game.variant = state.setup.variant;
+    // the server only knows the classic rules
+    #[cfg(not(target_arch = "wasm32"))]
+    if state.setup.mode == Mode::Online {
+        state.game.variant = Variant::Classic;
+    }
+    let (players, connect, exact) = state.setup.board();
+    state.game.players = players;
+    state.game.connect = connect;
+    state.game.exact = exact;
+    state.game.table = state.setup.table();
+    // network games and games of more than two are single games
+    state.match_play = Match::new(state.setup.length, state.settings.match_order)
+        .filter(|_| state.setup.mode == Mode::HotSeat && players == 2);
+    state.reset();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let setup = &state.setup;
+        match setup.mode {
+            Mode::HotSeat => {}
+            Mode::Online => {
+                let online = crate::online::Online::connect(&setup.server, &setup.name)
+                    .map_err(|e| format!("could not connect to {}: {}", setup.server, e))?;
+                state.online = Some(online);
+            }
+            Mode::HostLan => {
+                let lan = crate::lan::Lan::host(setup.port)
+                    .map_err(|e| format!("could not host on port {}: {}", setup.port, e))?;
+                state.lan = Some(lan);
+            }
+            Mode::JoinLan => {
+                let lan = crate::lan::Lan::join(&setup.peer, state.game.rules())
+                    .map_err(|e| format!("could not join {}: {}", setup.peer, e))?;
+                state.lan = Some(lan);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn draw(draw: &mut Draw, state: &State) {
+    let layout = &state.layout;
+    let (title, list, entries) = entries(state, state.scenes.top());
+
+    // the paused game stays in sight
+    if state.scenes.has_game() {
+        let (pos, size) = layout.area();
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .color(Color::GRAY)
+            .alpha(0.8);
+    }
+
+    let pos = layout.menu_title();
+    draw.text(&state.font, title)
+        .position(pos.x, pos.y)
+        .size(layout.len(56.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let (pos, size) = layout.menu_item(i);
+        let alpha = if i == list.selected { 0.5 } else { 0.15 };
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .color(Color::BLUE)
+            .alpha(alpha);
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .stroke_color(Color::BLUE)
+            .stroke(layout.len(2.0));
+
+        let center = pos + size * 0.5;
+        draw.text(&state.font, &entry.label(state))
+            .position(center.x, center.y)
+            .size(layout.len(24.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    if let (Scene::Menu(_), Some(error)) = (state.scenes.top(), &state.setup.error) {
+        let (pos, size) = layout.menu_item(entries.len());
+        let center = pos + size * 0.5;
+        draw.text(&state.font, error)
+            .position(center.x, center.y)
+            .size(layout.len(16.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::RED);
+    }
+}
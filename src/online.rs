@@ -0,0 +1,449 @@
+// Client side of network games: the lobby list and playing or spectating a
+// room. The server is authoritative, so moves are only applied to `State`
+// once the server echoes them back.
+// When the connection drops the client keeps dialing back with its session
+// token, the server then replays the game so far to restore the board.
+
+use notan::draw::*;
+use notan::prelude::*;
+
+use crate::board::{Move, Offer, Outcome, Player};
+use crate::client::Connection;
+use crate::clock::{control_name, TimeControl};
+use crate::input::Command;
+use crate::layout::{Layout, HEIGHT, MARGIN, WIDTH};
+use crate::panel::{self, Action, Controls};
+use crate::protocol::{ClientMessage, Role, RoomInfo, ServerMessage, DEFAULT_PORT, VERSION};
+use crate::sound::Effect;
+use crate::State;
+
+const RETRY_SECS: f32 = 2.0;
+
+pub struct Online {
+    conn: Connection,
+    addr: String,
+    name: String,
+    token: Option<String>,
+    retry_in: f32,
+    lobby: Vec<RoomInfo>,
+    selected: usize,
+    room: Option<String>,
+    role: Option<Role>,
+    started: bool,
+    status: String,
+}
+
+impl Online {
+    pub fn connect(addr: &str, name: &str) -> std::io::Result<Self> {
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{}:{}", addr, DEFAULT_PORT)
+        };
+
+        Ok(Online {
+            conn: hello(&addr, name, None)?,
+            status: format!("connecting to {}", addr),
+            addr,
+            name: name.to_string(),
+            token: None,
+            retry_in: RETRY_SECS,
+            lobby: vec![],
+            selected: 0,
+            room: None,
+            role: None,
+            started: false,
+        })
+    }
+
+    fn reconnect(&mut self, delta: f32) {
+        self.retry_in -= delta;
+        if self.retry_in > 0.0 {
+            return;
+        }
+        self.retry_in = RETRY_SECS;
+
+        match hello(&self.addr, &self.name, self.token.as_deref()) {
+            Ok(conn) => {
+                self.conn = conn;
+                self.status = "reconnecting".to_string();
+            }
+            Err(_) => self.status = "connection lost, retrying".to_string(),
+        }
+    }
+
+    pub fn in_lobby(&self) -> bool {
+        self.room.is_none()
+    }
+
+    fn my_turn(&self, turn: Player) -> bool {
+        self.started && self.role == Some(Role::Player(turn))
+    }
+
+    pub fn controls(&self) -> Controls {
+        match self.role {
+            Some(Role::Player(player)) if self.started => Controls::Player(player),
+            _ => Controls::Watching,
+        }
+    }
+
+    // text shown under the board while in a room
+    pub fn status(&self) -> String {
+        let room = self.room.as_deref().unwrap_or_default();
+        let role = match self.role {
+            Some(Role::Player(player)) => format!("playing {}", player.name()),
+            _ => "spectating".to_string(),
+        };
+
+        format!("Room {} - {} - {}", room, role, self.status)
+    }
+
+    pub fn end_hint(&self) -> &'static str {
+        match self.role {
+            Some(Role::Player(_)) => "Press SPACE for a rematch",
+            _ => "Waiting for a rematch",
+        }
+    }
+}
+
+// `token` resumes the session of an earlier connection
+fn hello(addr: &str, name: &str, token: Option<&str>) -> std::io::Result<Connection> {
+    let mut conn = Connection::open(addr)?;
+    conn.send(&ClientMessage::Hello {
+        version: VERSION,
+        name: name.to_string(),
+        token: token.map(|token| token.to_string()),
+    });
+
+    Ok(conn)
+}
+
+pub fn update(app: &mut App, state: &mut State) {
+    let Some(online) = &mut state.online else {
+        return;
+    };
+
+    for msg in online.conn.poll::<ServerMessage>() {
+        on_message(state, msg);
+    }
+
+    // only to show the time, the server calls time outs
+    state.game.tick(app.timer.delta_f32());
+
+    let Some(online) = &mut state.online else {
+        return;
+    };
+
+    // the board stays up while we try to get back into the game
+    if online.conn.is_closed() {
+        online.started = false;
+        online.reconnect(app.timer.delta_f32());
+        return;
+    }
+
+    if online.in_lobby() {
+        if app.keyboard.was_pressed(KeyCode::Escape) {
+            state.online = None;
+            state.scenes.back_to_menu();
+            return;
+        }
+
+        update_lobby(app, online, &mut state.game.time_control);
+        return;
+    }
+
+    // spectators can chat too, ESCAPE then cancels the line instead of leaving
+    let typing = state.chat.is_typing();
+    if let Some(text) = state.chat.update(&app.keyboard) {
+        online.conn.send(&ClientMessage::Chat { text });
+    }
+    if typing || state.chat.is_typing() {
+        return;
+    }
+
+    if app.keyboard.was_pressed(KeyCode::Escape) {
+        online.conn.send(&ClientMessage::Leave);
+        online.room = None;
+        online.role = None;
+        online.started = false;
+        state.game.start(state.game.first);
+        return;
+    }
+
+    if state.game.outcome.is_some() {
+        if state.input.pressed(Command::Restart) {
+            online.conn.send(&ClientMessage::Rematch);
+        }
+        return;
+    }
+
+    let controls = online.controls();
+    let my_turn = online.my_turn(state.game.turn);
+    if let Some(msg) = chosen(app, state, controls, my_turn) {
+        if let Some(online) = &mut state.online {
+            online.conn.send(&msg);
+        }
+    }
+}
+
+// the server answers with the outcome, offers are only shown once echoed
+fn chosen(app: &App, state: &State, controls: Controls, my_turn: bool) -> Option<ClientMessage> {
+    if state.game.is_animating() {
+        return None;
+    }
+
+    if let Some((mx, my)) = state.input.clicked(app) {
+        match panel::action_at(state, controls, mx, my) {
+            Some(Action::Resign) => return Some(ClientMessage::Resign),
+            Some(Action::OfferDraw) => return Some(ClientMessage::OfferDraw),
+            Some(Action::Takeback) => return Some(ClientMessage::RequestTakeback),
+            Some(action @ (Action::Accept | Action::Decline)) => {
+                let accept = action == Action::Accept;
+                return match state.game.offer? {
+                    Offer::Draw(_) => Some(ClientMessage::AnswerDraw { accept }),
+                    Offer::Takeback(_) => Some(ClientMessage::AnswerTakeback { accept }),
+                };
+            }
+            // the swap rule is only for hot seat games
+            Some(Action::Swap | Action::Keep) | None => {}
+        }
+    }
+
+    // no moves while looking back through the game
+    if state.game.offer.is_some() || !my_turn || state.browse.is_some() {
+        return None;
+    }
+
+    state
+        .input
+        .chosen_column(app, &state.layout)
+        .map(|col| ClientMessage::Move { col })
+}
+
+// `control` is the clock for rooms we create
+fn update_lobby(app: &mut App, online: &mut Online, control: &mut Option<TimeControl>) {
+    let keyboard = &app.keyboard;
+
+    if keyboard.was_pressed(KeyCode::Up) {
+        online.selected = online.selected.saturating_sub(1);
+    }
+
+    if keyboard.was_pressed(KeyCode::Down) && online.selected + 1 < online.lobby.len() {
+        online.selected += 1;
+    }
+
+    if keyboard.was_pressed(KeyCode::R) {
+        online.conn.send(&ClientMessage::List);
+    }
+
+    if keyboard.was_pressed(KeyCode::T) {
+        *control = TimeControl::cycle(*control);
+    }
+
+    if keyboard.was_pressed(KeyCode::C) {
+        let name = online.name.clone();
+        let clock = *control;
+        online.conn.send(&ClientMessage::Create { name, clock });
+    }
+
+    let Some(code) = online
+        .lobby
+        .get(online.selected)
+        .map(|room| room.code.clone())
+    else {
+        return;
+    };
+
+    if keyboard.was_pressed(KeyCode::Return) {
+        online.conn.send(&ClientMessage::Join { code });
+    } else if keyboard.was_pressed(KeyCode::W) {
+        online.conn.send(&ClientMessage::Watch { code });
+    }
+}
+
+fn on_message(state: &mut State, msg: ServerMessage) {
+    let Some(online) = &mut state.online else {
+        return;
+    };
+
+    match msg {
+        ServerMessage::Welcome { token, .. } => {
+            online.token = Some(token);
+            online.status = "connected".to_string();
+            // a session that could not be resumed starts over in the lobby
+            online.room = None;
+            online.role = None;
+        }
+        ServerMessage::Lobby(rooms) => {
+            online.lobby = rooms;
+            online.selected = online.selected.min(online.lobby.len().saturating_sub(1));
+        }
+        ServerMessage::Joined { code, role } => {
+            online.room = Some(code);
+            online.role = Some(role);
+            online.started = false;
+            online.status = "waiting for an opponent".to_string();
+            state.reset();
+        }
+        ServerMessage::Start { first, clock } => {
+            online.started = true;
+            online.status = "game on".to_string();
+            state.game.time_control = clock;
+            state.game.start(first);
+        }
+        ServerMessage::Moved { player, col } => {
+            state.game.offer = None;
+            state.game.turn = player;
+            state.game.play(Move::Drop(col));
+        }
+        ServerMessage::Sync {
+            first,
+            clock,
+            offer,
+            outcome,
+            moves,
+        } => {
+            online.started = true;
+            online.status = "game on".to_string();
+            state.game.time_control = clock;
+            state.game.start(first);
+            for col in moves {
+                state.game.play(Move::Drop(col));
+            }
+            state.game.offer = offer;
+            // resignations and the like don't show on the board
+            if !state.game.game_over() {
+                state.game.outcome = outcome;
+            }
+        }
+        ServerMessage::Clock { red, yellow } => {
+            if let Some(clock) = &mut state.game.clock {
+                clock.set_left(Player::Red, red);
+                clock.set_left(Player::Yellow, yellow);
+            }
+        }
+        ServerMessage::Timeout { player } => {
+            state.game.offer = None;
+            state.game.outcome = Some(Outcome::Timeout(player));
+        }
+        ServerMessage::Away { player } => {
+            online.status = format!("{} lost the connection, waiting", player.name());
+        }
+        ServerMessage::Back { player } => {
+            online.status = format!("{} is back, game on", player.name());
+        }
+        ServerMessage::Forfeit { player } => {
+            online.status = format!("{} forfeited", player.name());
+            if !state.game.game_over() {
+                state.game.outcome = Some(Outcome::Forfeit(player));
+            }
+        }
+        ServerMessage::Resigned { player } => {
+            state.game.offer = None;
+            state.game.outcome = Some(Outcome::Resigned(player));
+        }
+        ServerMessage::DrawOffered { player } => state.game.offer = Some(Offer::Draw(player)),
+        ServerMessage::TakebackRequested { player } => {
+            state.game.offer = Some(Offer::Takeback(player));
+        }
+        ServerMessage::DrawAnswered { accept } | ServerMessage::TakebackAnswered { accept } => {
+            state.game.answer_offer(accept);
+        }
+        ServerMessage::Chat { name, text } => state.chat.push(format!("{}: {}", name, text)),
+        ServerMessage::Left { role } => {
+            online.started = false;
+            online.status = match role {
+                Role::Player(player) => format!("{} left, waiting for a new player", player.name()),
+                Role::Spectator => "a spectator left".to_string(),
+            };
+        }
+        ServerMessage::Closed => {
+            online.room = None;
+            online.role = None;
+            online.started = false;
+            online.status = "the room was closed".to_string();
+        }
+        ServerMessage::Error(text) => {
+            // most likely a move the server turned down
+            state.sounds.queue(Effect::Invalid);
+            online.status = text;
+        }
+    }
+}
+
+pub fn draw_lobby(
+    draw: &mut Draw,
+    font: &Font,
+    layout: &Layout,
+    online: &Online,
+    control: Option<TimeControl>,
+) {
+    let pos = layout.point(WIDTH * 0.5, MARGIN);
+    draw.text(font, "Lobby")
+        .position(pos.x, pos.y)
+        .size(layout.len(48.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    if online.lobby.is_empty() {
+        let pos = layout.point(WIDTH * 0.5, HEIGHT * 0.4);
+        draw.text(font, "No open rooms yet")
+            .position(pos.x, pos.y)
+            .size(layout.len(24.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    for (i, room) in online.lobby.iter().enumerate() {
+        let y = MARGIN * 2.0 + i as f32 * 32.0;
+        if i == online.selected {
+            let pos = layout.point(MARGIN, y - 16.0);
+            draw.rect(
+                (pos.x, pos.y),
+                (layout.len(WIDTH - MARGIN * 2.0), layout.len(32.0)),
+            )
+            .color(Color::BLUE)
+            .alpha(0.3);
+        }
+
+        let seats = if room.players < 2 { "open" } else { "playing" };
+        let text = format!(
+            "{}  {}  {}/2 {}  {} watching  clock {}",
+            room.code,
+            room.name,
+            room.players,
+            seats,
+            room.spectators,
+            control_name(room.clock)
+        );
+
+        let pos = layout.point(MARGIN + 10.0, y);
+        draw.text(font, &text)
+            .position(pos.x, pos.y)
+            .size(layout.len(20.0))
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    let pos = layout.point(WIDTH * 0.5, HEIGHT - MARGIN * 1.5);
+    draw.text(font, &online.status)
+        .position(pos.x, pos.y)
+        .size(layout.len(18.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+
+    let keys = format!(
+        "C create  T clock: {}  ENTER join  W watch  R refresh",
+        control_name(control)
+    );
+    let pos = layout.point(WIDTH * 0.5, HEIGHT - MARGIN * 0.75);
+    draw.text(font, &keys)
+        .position(pos.x, pos.y)
+        .size(layout.len(18.0))
+        .h_align_center()
+        .v_align_middle()
+        .color(Color::BLACK);
+}
@@ -0,0 +1,72 @@
+// Command line options, e.g. `cargo run -- --connect 127.0.0.1:7878 --name alice`
+// or `cargo run -- --server 7878` for a headless server.
+// LAN games without a server use `--host [port]` on one side and
+// `--join <address>` on the other.
+// `--clock 3+2` plays with chess clocks, see `clock.rs` for the notation.
+// `--bind <command>=<key>` changes the controls, see `input.rs`.
+// `--seed <number>` makes the random choices repeat, saved games name theirs.
+
+use crate::clock::TimeControl;
+use crate::protocol::{sanitize_name, DEFAULT_PORT};
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub server: Option<u16>,
+    pub connect: Option<String>,
+    pub name: Option<String>,
+    pub host: Option<u16>,
+    pub join: Option<String>,
+    // `Some(None)` is an explicit `--clock none`
+    pub clock: Option<Option<TimeControl>>,
+    pub bind: Vec<String>,
+    pub seed: Option<u64>,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        let mut options = Options::default();
+        let mut args = std::env::args().skip(1).peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--server" => {
+                    let port = args.next_if(|value| !value.starts_with("--"));
+                    options.server =
+                        Some(port.and_then(|p| p.parse().ok()).unwrap_or(DEFAULT_PORT));
+                }
+                "--connect" => options.connect = args.next(),
+                "--host" => {
+                    let port = args.next_if(|value| !value.starts_with("--"));
+                    options.host = Some(port.and_then(|p| p.parse().ok()).unwrap_or(DEFAULT_PORT));
+                }
+                "--join" => options.join = args.next(),
+                "--clock" => {
+                    let name = args.next().unwrap_or_default();
+                    let control = TimeControl::from_name(&name);
+                    if control.is_some() || name == "none" {
+                        options.clock = Some(control);
+                    } else {
+                        eprintln!("ignoring unknown time control '{}'", name);
+                    }
+                }
+                "--bind" => options.bind.extend(args.next()),
+                "--seed" => {
+                    let seed = args.next().unwrap_or_default();
+                    options.seed = seed.parse().ok();
+                    if options.seed.is_none() {
+                        eprintln!("ignoring seed '{}', it has to be a number", seed);
+                    }
+                }
+                "--name" => options.name = args.next().map(|name| sanitize_name(&name)),
+                _ => eprintln!("ignoring unknown argument '{}'", arg),
+            }
+        }
+
+        options
+    }
+
+    // `--connect`, `--host` or `--join` go straight into that game
+    pub fn picks_mode(&self) -> bool {
+        self.connect.is_some() || self.host.is_some() || self.join.is_some()
+    }
+}
@@ -0,0 +1,232 @@
+// Game flow controls under the board: resigning, draw offers, takeback
+// requests, the choice of the swap rule and, in network games, a short chat.
+
+use notan::draw::*;
+use notan::math::Vec2;
+use notan::prelude::*;
+
+use crate::board::{Offer, Player};
+use crate::layout::{Layout, HEIGHT, MARGIN};
+use crate::protocol::sanitize_chat;
+use crate::State;
+
+const BUTTON_SIZE: Vec2 = Vec2::new(150.0, 36.0);
+const BUTTON_GAP: f32 = 25.0;
+const CHAT_LINES: usize = 3;
+const CHAT_LENGTH: usize = 80;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Action {
+    Resign,
+    OfferDraw,
+    Takeback,
+    Accept,
+    Decline,
+    // answers to the swap rule
+    Swap,
+    Keep,
+}
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::Resign => "Resign",
+            Action::OfferDraw => "Offer draw",
+            Action::Takeback => "Take back",
+            Action::Accept => "Accept",
+            Action::Decline => "Decline",
+            Action::Swap => "Swap",
+            Action::Keep => "Keep colour",
+        }
+    }
+}
+
+// who can use the controls on this device
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Controls {
+    HotSeat,
+    Player(Player),
+    Watching,
+}
+
+#[derive(Default)]
+pub struct Chat {
+    lines: Vec<String>,
+    input: String,
+    typing: bool,
+}
+
+impl Chat {
+    pub fn push(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    pub fn on_char(&mut self, c: char) {
+        if self.typing && !c.is_control() && self.input.chars().count() < CHAT_LENGTH {
+            self.input.push(c);
+        }
+    }
+
+    // TAB starts typing, ENTER sends the line and ESCAPE drops it
+    pub fn update(&mut self, keyboard: &Keyboard) -> Option<String> {
+        if !self.typing {
+            self.typing = keyboard.was_pressed(KeyCode::Tab);
+            return None;
+        }
+
+        if keyboard.was_pressed(KeyCode::Escape) {
+            self.typing = false;
+            self.input.clear();
+        } else if keyboard.was_pressed(KeyCode::Back) {
+            self.input.pop();
+        } else if keyboard.was_pressed(KeyCode::Return) {
+            self.typing = false;
+            let text = sanitize_chat(&std::mem::take(&mut self.input));
+            return (!text.is_empty()).then_some(text);
+        }
+
+        None
+    }
+}
+
+fn prompt(state: &State, offer: Offer) -> String {
+    match offer {
+        Offer::Draw(player) => format!("{} offers a draw", state.player_name(player)),
+        Offer::Takeback(player) => {
+            format!("{} asks to take back a move", state.player_name(player))
+        }
+    }
+}
+
+pub fn buttons(state: &State, controls: Controls) -> Vec<Action> {
+    if state.game.outcome.is_some() || crate::fog::is_down(state) {
+        return vec![];
+    }
+
+    let me = match controls {
+        Controls::HotSeat => None,
+        Controls::Player(player) => Some(player),
+        Controls::Watching => return vec![],
+    };
+
+    // only hot seat games have the swap rule
+    if state.game.swap_offered {
+        return vec![Action::Swap, Action::Keep];
+    }
+
+    if let Some(offer) = state.game.offer {
+        // our own requests just wait for the answer
+        return if Some(offer.by()) == me {
+            vec![]
+        } else {
+            vec![Action::Accept, Action::Decline]
+        };
+    }
+
+    // only the player that moved last can take it back
+    let last = state.game.last_mover();
+    let can_take_back = last.is_some() && me.is_none_or(|me| Some(me) == last);
+    let mut actions = vec![Action::Resign];
+    // with more than two, players who have had enough resign and the rest
+    // play on
+    if state.game.players == 2 {
+        actions.push(Action::OfferDraw);
+    }
+    if can_take_back {
+        actions.push(Action::Takeback);
+    }
+
+    actions
+}
+
+// top left corner and size of a button on the screen
+fn button_rect(layout: &Layout, index: usize) -> (Vec2, Vec2) {
+    let x = MARGIN + index as f32 * (BUTTON_SIZE.x + BUTTON_GAP);
+    (layout.point(x, HEIGHT + 10.0), BUTTON_SIZE * layout.scale)
+}
+
+pub fn action_at(state: &State, controls: Controls, mx: f32, my: f32) -> Option<Action> {
+    buttons(state, controls)
+        .into_iter()
+        .enumerate()
+        .find(|&(i, _)| {
+            let (pos, size) = button_rect(&state.layout, i);
+            (pos.x..pos.x + size.x).contains(&mx) && (pos.y..pos.y + size.y).contains(&my)
+        })
+        .map(|(_, action)| action)
+}
+
+pub fn draw_panel(
+    draw: &mut Draw,
+    font: &Font,
+    layout: &Layout,
+    state: &State,
+    controls: Controls,
+    chat: bool,
+) {
+    for (i, action) in buttons(state, controls).into_iter().enumerate() {
+        let (pos, size) = button_rect(layout, i);
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .color(Color::BLUE)
+            .alpha(0.3);
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .stroke_color(Color::BLUE)
+            .stroke(layout.len(2.0));
+
+        let center = pos + size * 0.5;
+        draw.text(font, action.label())
+            .position(center.x, center.y)
+            .size(layout.len(18.0))
+            .h_align_center()
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    let game = &state.game;
+    let text = match game.offer {
+        Some(offer) => Some(prompt(state, offer)),
+        None => game.swap_offered.then(|| {
+            let name = state.player_name(game.turn);
+            format!("{} may take over the first disc or keep their colour", name)
+        }),
+    };
+    if let Some(text) = text {
+        let pos = layout.point(MARGIN, HEIGHT + 62.0);
+        draw.text(font, &text)
+            .position(pos.x, pos.y)
+            .size(layout.len(18.0))
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    if !chat {
+        return;
+    }
+
+    let first = state.chat.lines.len().saturating_sub(CHAT_LINES);
+    for (i, line) in state.chat.lines[first..].iter().enumerate() {
+        let pos = layout.point(MARGIN, HEIGHT + 84.0 + i as f32 * 18.0);
+        draw.text(font, line)
+            .position(pos.x, pos.y)
+            .size(layout.len(14.0))
+            .v_align_middle()
+            .color(Color::BLACK);
+    }
+
+    let input = if state.chat.typing {
+        format!("> {}_", state.chat.input)
+    } else {
+        "Press TAB to chat".to_string()
+    };
+
+    let pos = layout.point(MARGIN, HEIGHT + 142.0);
+    draw.text(font, &input)
+        .position(pos.x, pos.y)
+        .size(layout.len(14.0))
+        .v_align_middle()
+        .color(Color::BLACK);
+}
@@ -0,0 +1,153 @@
+// The power-up tray of the PowerUp variant, at the right of the panel under
+// the board. It shows the special discs the player on turn has left; clicking
+// one or pressing UP picks it, and the next column played gets it instead of
+// a plain disc. Clicking it again puts it back.
+
+use notan::draw::*;
+use notan::math::{vec2, Vec2};
+use notan::prelude::*;
+
+use crate::board::{Move, Player, Power, Variant};
+use crate::input::Command;
+use crate::layout::{Layout, HEIGHT, MARGIN, WIDTH};
+use crate::theme::contrast;
+use crate::State;
+
+const SLOT: f32 = 40.0;
+const SLOT_GAP: f32 = 8.0;
+
+// the power-up picked for the next move, if the player on turn still has it
+pub fn picked(state: &State) -> Option<Power> {
+    let (player, power) = state.power?;
+    let game = &state.game;
+    (player == game.turn && game.has_power(player, power)).then_some(power)
+}
+
+// a plain disc becomes the picked power-up
+pub fn arm(state: &State, mv: Move) -> Move {
+    match (mv, picked(state)) {
+        (Move::Drop(col), Some(power)) => Move::Power(power, col),
+        _ => mv,
+    }
+}
+
+// takes the clicks and keys meant for the tray, only while the player on
+// turn may move
+pub fn update(app: &App, state: &mut State) {
+    if state.game.variant != Variant::PowerUp {
+        return;
+    }
+
+    let player = state.game.turn;
+    let left = |state: &State| {
+        Power::ALL
+            .into_iter()
+            .filter(|&power| state.game.has_power(player, power))
+            .collect::<Vec<_>>()
+    };
+
+    if let Some((mx, my)) = state.input.clicked(app) {
+        let slot = Power::ALL.into_iter().enumerate().find(|&(i, _)| {
+            let (pos, size) = slot_rect(&state.layout, i);
+            (pos.x..pos.x + size.x).contains(&mx) && (pos.y..pos.y + size.y).contains(&my)
+        });
+        if let Some((_, power)) = slot {
+            state.input.take_tap();
+            if picked(state) == Some(power) {
+                state.power = None;
+            } else if left(state).contains(&power) {
+                state.power = Some((player, power));
+            }
+        }
+    }
+
+    // UP goes through the ones left and then back to a plain disc
+    if state.input.take(Command::Power) {
+        let left = left(state);
+        let next = match picked(state) {
+            Some(power) => left.iter().skip_while(|&&p| p != power).nth(1).copied(),
+            None => left.first().copied(),
+        };
+        state.power = next.map(|power| (player, power));
+    }
+}
+
+// top left corner and size of the slot of the `i`th power-up
+fn slot_rect(layout: &Layout, i: usize) -> (Vec2, Vec2) {
+    let count = Power::ALL.len() as f32;
+    let x = WIDTH - MARGIN - count * SLOT - (count - 1.0) * SLOT_GAP;
+    let x = x + i as f32 * (SLOT + SLOT_GAP);
+    (
+        layout.point(x, HEIGHT + 56.0),
+        vec2(SLOT, SLOT) * layout.scale,
+    )
+}
+
+pub fn draw(draw: &mut Draw, state: &State) {
+    let game = &state.game;
+    if game.variant != Variant::PowerUp || game.game_over() || state.browse.is_some() {
+        return;
+    }
+
+    let layout = &state.layout;
+    let player = game.turn;
+    let radius = layout.len(SLOT * 0.4);
+    for (i, power) in Power::ALL.into_iter().enumerate() {
+        let (pos, size) = slot_rect(layout, i);
+        if picked(state) == Some(power) {
+            draw.rect((pos.x, pos.y), (size.x, size.y))
+                .color(Color::BLUE)
+                .alpha(0.3);
+        }
+        draw.rect((pos.x, pos.y), (size.x, size.y))
+            .stroke_color(Color::BLUE)
+            .stroke(layout.len(2.0));
+
+        let center = pos + size * 0.5;
+        draw_icon(draw, state, player, power, radius, center);
+
+        // used ones stay in sight, greyed out
+        if !game.has_power(player, power) {
+            draw.rect((pos.x, pos.y), (size.x, size.y))
+                .color(Color::GRAY)
+                .alpha(0.8);
+        }
+    }
+}
+
+fn draw_icon(draw: &mut Draw, state: &State, player: Player, power: Power, radius: f32, pos: Vec2) {
+    let style = state.settings.style();
+    if power == Power::Wall {
+        style.draw_wall(draw, radius, pos);
+        return;
+    }
+
+    let color = style.color(player);
+    draw.circle(radius)
+        .position(pos.x, pos.y)
+        .fill_color(color)
+        .fill();
+
+    let label = match power {
+        Power::Anvil => "A",
+        Power::Bomb => "B",
+        _ => "x2",
+    };
+    draw.text(&state.font, label)
+        .position(pos.x, pos.y)
+        .size(radius * 1.1)
+        .h_align_center()
+        .v_align_middle()
+        .color(contrast(color));
+}
+
+// what the picked power-up does, for the status line under the board
+pub fn status(state: &State) -> Option<String> {
+    let text = match picked(state)? {
+        Power::Anvil => "Anvil: clears the column it lands in",
+        Power::Bomb => "Bomb: blows up an opponent's disc next to where it lands",
+        Power::Wall => "Wall: a block that belongs to nobody",
+        Power::Double => "x2: play again after this disc",
+    };
+    Some(text.to_string())
+}
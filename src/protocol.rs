@@ -0,0 +1,954 @@
+// Line based messages shared by the server, the client and LAN peers.
+// Every message is a single line of space separated words, the first word
+// being the message name. Clients open with `HELLO <version> <name> [token]`,
+// passing the session token from a previous `WELCOME` to resume a game.
+// Time controls travel by name, `none` for games without clocks, and so do
+// pending offers and outcomes, like `draw:red` or `resigned:yellow`.
+
+use crate::board::{Move, Offer, Outcome, Player, Variant};
+use crate::clock::{control_name, TimeControl};
+use crate::game::Rules;
+
+pub const VERSION: u32 = 6;
+pub const DEFAULT_PORT: u16 = 7878;
+
+pub trait Message: Sized {
+    fn encode(&self) -> String;
+    fn decode(line: &str) -> Option<Self>;
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Role {
+    Player(Player),
+    Spectator,
+}
+
+impl Role {
+    fn name(self) -> &'static str {
+        match self {
+            Role::Player(player) => player.name(),
+            Role::Spectator => "spectator",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Role> {
+        match name {
+            "spectator" => Some(Role::Spectator),
+            _ => Player::from_name(name).map(Role::Player),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoomInfo {
+    pub code: String,
+    pub name: String,
+    pub players: usize,
+    pub spectators: usize,
+    pub clock: Option<TimeControl>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClientMessage {
+    Hello {
+        version: u32,
+        name: String,
+        token: Option<String>,
+    },
+    List,
+    Create {
+        name: String,
+        clock: Option<TimeControl>,
+    },
+    Join {
+        code: String,
+    },
+    Watch {
+        code: String,
+    },
+    Move {
+        col: usize,
+    },
+    Rematch,
+    Leave,
+    Resign,
+    OfferDraw,
+    AnswerDraw {
+        accept: bool,
+    },
+    RequestTakeback,
+    AnswerTakeback {
+        accept: bool,
+    },
+    Chat {
+        text: String,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerMessage {
+    Welcome {
+        version: u32,
+        token: String,
+    },
+    Lobby(Vec<RoomInfo>),
+    Joined {
+        code: String,
+        role: Role,
+    },
+    Start {
+        first: Player,
+        clock: Option<TimeControl>,
+    },
+    Moved {
+        player: Player,
+        col: usize,
+    },
+    // full move list of the running game, sent to late joiners, with the
+    // offer waiting for an answer and how the game ended if it did
+    Sync {
+        first: Player,
+        clock: Option<TimeControl>,
+        offer: Option<Offer>,
+        outcome: Option<Outcome>,
+        moves: Vec<usize>,
+    },
+    // seconds left on both clocks, sent after every move
+    Clock {
+        red: f32,
+        yellow: f32,
+    },
+    Timeout {
+        player: Player,
+    },
+    // a player lost the connection and has a grace period to come back
+    Away {
+        player: Player,
+    },
+    Back {
+        player: Player,
+    },
+    Forfeit {
+        player: Player,
+    },
+    Left {
+        role: Role,
+    },
+    Closed,
+    Resigned {
+        player: Player,
+    },
+    DrawOffered {
+        player: Player,
+    },
+    DrawAnswered {
+        accept: bool,
+    },
+    TakebackRequested {
+        player: Player,
+    },
+    TakebackAnswered {
+        accept: bool,
+    },
+    Chat {
+        name: String,
+        text: String,
+    },
+    Error(String),
+}
+
+// messages between the two sides of a LAN game, the host is authoritative:
+// the guest asks for moves and rematches, the host confirms them
+#[derive(Clone, PartialEq, Debug)]
+pub enum PeerMessage {
+    // none if the guest's rules could not be read, so they can still be
+    // rejected, e.g. an unknown variant
+    Hello {
+        version: u32,
+        rules: Option<Rules>,
+    },
+    Accept {
+        rules: Rules,
+        guest: Player,
+        first: Player,
+        clock: Option<TimeControl>,
+    },
+    Reject(String),
+    // pops are written like `p3`, see `Move::name`
+    // moves, offers and answers are the host's to confirm
+    Move {
+        player: Player,
+        mv: Move,
+    },
+    Rematch,
+    Start {
+        first: Player,
+    },
+    Resign {
+        player: Player,
+    },
+    OfferDraw {
+        player: Player,
+    },
+    AnswerDraw {
+        accept: bool,
+    },
+    RequestTakeback {
+        player: Player,
+    },
+    AnswerTakeback {
+        accept: bool,
+    },
+    Chat {
+        text: String,
+    },
+    // the host keeps the time and calls it
+    Timeout {
+        player: Player,
+    },
+}
+
+// names travel as a single word
+pub fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| {
+            if c.is_whitespace() || c == ',' {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(16)
+        .collect();
+
+    if name.is_empty() {
+        "player".to_string()
+    } else {
+        name
+    }
+}
+
+// chat lines are kept short and on a single line
+pub fn sanitize_chat(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(80)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn yes_no(accept: bool) -> &'static str {
+    if accept {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn parse_yes_no(word: &str) -> Option<bool> {
+    match word {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_control(word: &str) -> Option<Option<TimeControl>> {
+    match word {
+        "none" => Some(None),
+        _ => TimeControl::from_name(word).map(Some),
+    }
+}
+
+// `<variant> <players> <connect> <exact|any> <cols>x<rows>`
+fn rules_words(rules: &Rules) -> String {
+    format!(
+        "{} {} {} {} {}x{}",
+        rules.variant.name(),
+        rules.players,
+        rules.connect,
+        if rules.exact { "exact" } else { "any" },
+        rules.board.0,
+        rules.board.1
+    )
+}
+
+fn parse_rules<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<Rules> {
+    let variant = Variant::from_name(words.next()?)?;
+    let players = words.next()?.parse().ok()?;
+    let connect = words.next()?.parse().ok()?;
+    let exact = match words.next()? {
+        "exact" => true,
+        "any" => false,
+        _ => return None,
+    };
+    let (cols, rows) = words.next()?.split_once('x')?;
+
+    Some(Rules {
+        variant,
+        players,
+        connect,
+        exact,
+        board: (cols.parse().ok()?, rows.parse().ok()?),
+    })
+}
+
+fn offer_name(offer: Option<Offer>) -> String {
+    match offer {
+        Some(Offer::Draw(player)) => format!("draw:{}", player.name()),
+        Some(Offer::Takeback(player)) => format!("takeback:{}", player.name()),
+        None => "none".to_string(),
+    }
+}
+
+fn parse_offer(word: &str) -> Option<Option<Offer>> {
+    if word == "none" {
+        return Some(None);
+    }
+
+    let (kind, player) = word.split_once(':')?;
+    let player = Player::from_name(player)?;
+    match kind {
+        "draw" => Some(Some(Offer::Draw(player))),
+        "takeback" => Some(Some(Offer::Takeback(player))),
+        _ => None,
+    }
+}
+
+fn outcome_name(outcome: Option<Outcome>) -> String {
+    let Some(outcome) = outcome else {
+        return "none".to_string();
+    };
+
+    let (how, player) = match outcome {
+        Outcome::Connected(player) => ("connected", Some(player)),
+        Outcome::Full => ("full", None),
+        Outcome::Resigned(player) => ("resigned", Some(player)),
+        Outcome::AgreedDraw => ("agreed", None),
+        Outcome::Forfeit(player) => ("forfeit", Some(player)),
+        Outcome::Timeout(player) => ("time", Some(player)),
+        Outcome::Repetition => ("repetition", None),
+        Outcome::Completed(player) => ("four", Some(player)),
+        Outcome::Outlasted(player) => ("outlasted", Some(player)),
+    };
+
+    match player {
+        Some(player) => format!("{}:{}", how, player.name()),
+        None => how.to_string(),
+    }
+}
+
+fn parse_outcome(word: &str) -> Option<Option<Outcome>> {
+    let outcome = match word.split_once(':') {
+        None => match word {
+            "none" => return Some(None),
+            "full" => Outcome::Full,
+            "agreed" => Outcome::AgreedDraw,
+            "repetition" => Outcome::Repetition,
+            _ => return None,
+        },
+        Some((how, player)) => {
+            let player = Player::from_name(player)?;
+            match how {
+                "connected" => Outcome::Connected(player),
+                "resigned" => Outcome::Resigned(player),
+                "forfeit" => Outcome::Forfeit(player),
+                "time" => Outcome::Timeout(player),
+                "four" => Outcome::Completed(player),
+                "outlasted" => Outcome::Outlasted(player),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(Some(outcome))
+}
+
+fn rest<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    words.collect::<Vec<_>>().join(" ")
+}
+
+impl Message for ClientMessage {
+    fn encode(&self) -> String {
+        match self {
+            ClientMessage::Hello {
+                version,
+                name,
+                token,
+            } => match token {
+                Some(token) => format!("HELLO {} {} {}", version, name, token),
+                None => format!("HELLO {} {}", version, name),
+            },
+            ClientMessage::List => "LIST".to_string(),
+            ClientMessage::Create { name, clock } => {
+                format!("CREATE {} {}", name, control_name(*clock))
+            }
+            ClientMessage::Join { code } => format!("JOIN {}", code),
+            ClientMessage::Watch { code } => format!("WATCH {}", code),
+            ClientMessage::Move { col } => format!("MOVE {}", col),
+            ClientMessage::Rematch => "REMATCH".to_string(),
+            ClientMessage::Leave => "LEAVE".to_string(),
+            ClientMessage::Resign => "RESIGN".to_string(),
+            ClientMessage::OfferDraw => "OFFER_DRAW".to_string(),
+            ClientMessage::AnswerDraw { accept } => format!("ANSWER_DRAW {}", yes_no(*accept)),
+            ClientMessage::RequestTakeback => "TAKEBACK".to_string(),
+            ClientMessage::AnswerTakeback { accept } => {
+                format!("ANSWER_TAKEBACK {}", yes_no(*accept))
+            }
+            ClientMessage::Chat { text } => format!("CHAT {}", text),
+        }
+    }
+
+    fn decode(line: &str) -> Option<ClientMessage> {
+        let mut words = line.split_whitespace();
+        let msg = match words.next()? {
+            "HELLO" => ClientMessage::Hello {
+                version: words.next()?.parse().ok()?,
+                name: sanitize_name(words.next().unwrap_or_default()),
+                token: words.next().map(|token| token.to_string()),
+            },
+            "LIST" => ClientMessage::List,
+            "CREATE" => ClientMessage::Create {
+                name: sanitize_name(words.next().unwrap_or_default()),
+                clock: words.next().map_or(Some(None), parse_control)?,
+            },
+            "JOIN" => ClientMessage::Join {
+                code: words.next()?.to_uppercase(),
+            },
+            "WATCH" => ClientMessage::Watch {
+                code: words.next()?.to_uppercase(),
+            },
+            "MOVE" => ClientMessage::Move {
+                col: words.next()?.parse().ok()?,
+            },
+            "REMATCH" => ClientMessage::Rematch,
+            "LEAVE" => ClientMessage::Leave,
+            "RESIGN" => ClientMessage::Resign,
+            "OFFER_DRAW" => ClientMessage::OfferDraw,
+            "ANSWER_DRAW" => ClientMessage::AnswerDraw {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "TAKEBACK" => ClientMessage::RequestTakeback,
+            "ANSWER_TAKEBACK" => ClientMessage::AnswerTakeback {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "CHAT" => ClientMessage::Chat {
+                text: sanitize_chat(&rest(words)),
+            },
+            _ => return None,
+        };
+
+        Some(msg)
+    }
+}
+
+impl Message for ServerMessage {
+    fn encode(&self) -> String {
+        match self {
+            ServerMessage::Welcome { version, token } => format!("WELCOME {} {}", version, token),
+            ServerMessage::Lobby(rooms) => {
+                let mut line = "LOBBY".to_string();
+                for room in rooms {
+                    line.push_str(&format!(
+                        " {},{},{},{},{}",
+                        room.code,
+                        room.players,
+                        room.spectators,
+                        control_name(room.clock),
+                        room.name
+                    ));
+                }
+                line
+            }
+            ServerMessage::Joined { code, role } => format!("JOINED {} {}", code, role.name()),
+            ServerMessage::Start { first, clock } => {
+                format!("START {} {}", first.name(), control_name(*clock))
+            }
+            ServerMessage::Moved { player, col } => format!("MOVED {} {}", player.name(), col),
+            ServerMessage::Sync {
+                first,
+                clock,
+                offer,
+                outcome,
+                moves,
+            } => {
+                let mut line = format!(
+                    "SYNC {} {} {} {}",
+                    first.name(),
+                    control_name(*clock),
+                    offer_name(*offer),
+                    outcome_name(*outcome)
+                );
+                for col in moves {
+                    line.push_str(&format!(" {}", col));
+                }
+                line
+            }
+            ServerMessage::Clock { red, yellow } => format!("CLOCK {:.1} {:.1}", red, yellow),
+            ServerMessage::Timeout { player } => format!("TIMEOUT {}", player.name()),
+            ServerMessage::Away { player } => format!("AWAY {}", player.name()),
+            ServerMessage::Back { player } => format!("BACK {}", player.name()),
+            ServerMessage::Forfeit { player } => format!("FORFEIT {}", player.name()),
+            ServerMessage::Left { role } => format!("LEFT {}", role.name()),
+            ServerMessage::Closed => "CLOSED".to_string(),
+            ServerMessage::Resigned { player } => format!("RESIGNED {}", player.name()),
+            ServerMessage::DrawOffered { player } => format!("DRAW_OFFERED {}", player.name()),
+            ServerMessage::DrawAnswered { accept } => {
+                format!("DRAW_ANSWERED {}", yes_no(*accept))
+            }
+            ServerMessage::TakebackRequested { player } => {
+                format!("TAKEBACK_REQUESTED {}", player.name())
+            }
+            ServerMessage::TakebackAnswered { accept } => {
+                format!("TAKEBACK_ANSWERED {}", yes_no(*accept))
+            }
+            ServerMessage::Chat { name, text } => format!("CHAT {} {}", name, text),
+            ServerMessage::Error(text) => format!("ERROR {}", text),
+        }
+    }
+
+    fn decode(line: &str) -> Option<ServerMessage> {
+        let mut words = line.split_whitespace();
+        let msg = match words.next()? {
+            "WELCOME" => ServerMessage::Welcome {
+                version: words.next()?.parse().ok()?,
+                token: words.next()?.to_string(),
+            },
+            "LOBBY" => {
+                let rooms = words
+                    .map(|entry| {
+                        let mut fields = entry.splitn(5, ',');
+                        Some(RoomInfo {
+                            code: fields.next()?.to_string(),
+                            players: fields.next()?.parse().ok()?,
+                            spectators: fields.next()?.parse().ok()?,
+                            clock: parse_control(fields.next()?)?,
+                            name: fields.next()?.to_string(),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                ServerMessage::Lobby(rooms)
+            }
+            "JOINED" => ServerMessage::Joined {
+                code: words.next()?.to_string(),
+                role: Role::from_name(words.next()?)?,
+            },
+            "START" => ServerMessage::Start {
+                first: Player::from_name(words.next()?)?,
+                clock: parse_control(words.next()?)?,
+            },
+            "MOVED" => ServerMessage::Moved {
+                player: Player::from_name(words.next()?)?,
+                col: words.next()?.parse().ok()?,
+            },
+            "SYNC" => ServerMessage::Sync {
+                first: Player::from_name(words.next()?)?,
+                clock: parse_control(words.next()?)?,
+                offer: parse_offer(words.next()?)?,
+                outcome: parse_outcome(words.next()?)?,
+                moves: words
+                    .map(|col| col.parse().ok())
+                    .collect::<Option<Vec<_>>>()?,
+            },
+            "CLOCK" => ServerMessage::Clock {
+                red: words.next()?.parse().ok()?,
+                yellow: words.next()?.parse().ok()?,
+            },
+            "TIMEOUT" => ServerMessage::Timeout {
+                player: Player::from_name(words.next()?)?,
+            },
+            "AWAY" => ServerMessage::Away {
+                player: Player::from_name(words.next()?)?,
+            },
+            "BACK" => ServerMessage::Back {
+                player: Player::from_name(words.next()?)?,
+            },
+            "FORFEIT" => ServerMessage::Forfeit {
+                player: Player::from_name(words.next()?)?,
+            },
+            "LEFT" => ServerMessage::Left {
+                role: Role::from_name(words.next()?)?,
+            },
+            "CLOSED" => ServerMessage::Closed,
+            "RESIGNED" => ServerMessage::Resigned {
+                player: Player::from_name(words.next()?)?,
+            },
+            "DRAW_OFFERED" => ServerMessage::DrawOffered {
+                player: Player::from_name(words.next()?)?,
+            },
+            "DRAW_ANSWERED" => ServerMessage::DrawAnswered {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "TAKEBACK_REQUESTED" => ServerMessage::TakebackRequested {
+                player: Player::from_name(words.next()?)?,
+            },
+            "TAKEBACK_ANSWERED" => ServerMessage::TakebackAnswered {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "CHAT" => ServerMessage::Chat {
+                name: words.next()?.to_string(),
+                text: rest(words),
+            },
+            "ERROR" => ServerMessage::Error(rest(words)),
+            _ => return None,
+        };
+
+        Some(msg)
+    }
+}
+
+impl Message for PeerMessage {
+    fn encode(&self) -> String {
+        match self {
+            PeerMessage::Hello { version, rules } => match rules {
+                Some(rules) => format!("HELLO {} {}", version, rules_words(rules)),
+                None => format!("HELLO {}", version),
+            },
+            PeerMessage::Accept {
+                rules,
+                guest,
+                first,
+                clock,
+            } => format!(
+                "ACCEPT {} {} {} {}",
+                guest.name(),
+                first.name(),
+                control_name(*clock),
+                rules_words(rules)
+            ),
+            PeerMessage::Reject(text) => format!("REJECT {}", text),
+            PeerMessage::Move { player, mv } => format!("MOVE {} {}", player.name(), mv.name()),
+            PeerMessage::Rematch => "REMATCH".to_string(),
+            PeerMessage::Start { first } => format!("START {}", first.name()),
+            PeerMessage::Resign { player } => format!("RESIGN {}", player.name()),
+            PeerMessage::OfferDraw { player } => format!("OFFER_DRAW {}", player.name()),
+            PeerMessage::AnswerDraw { accept } => format!("ANSWER_DRAW {}", yes_no(*accept)),
+            PeerMessage::RequestTakeback { player } => format!("TAKEBACK {}", player.name()),
+            PeerMessage::AnswerTakeback { accept } => {
+                format!("ANSWER_TAKEBACK {}", yes_no(*accept))
+            }
+            PeerMessage::Chat { text } => format!("CHAT {}", text),
+            PeerMessage::Timeout { player } => format!("TIMEOUT {}", player.name()),
+        }
+    }
+
+    fn decode(line: &str) -> Option<PeerMessage> {
+        let mut words = line.split_whitespace();
+        let msg = match words.next()? {
+            "HELLO" => PeerMessage::Hello {
+                version: words.next()?.parse().ok()?,
+                rules: parse_rules(&mut words),
+            },
+            "ACCEPT" => PeerMessage::Accept {
+                guest: Player::from_name(words.next()?)?,
+                first: Player::from_name(words.next()?)?,
+                clock: parse_control(words.next()?)?,
+                rules: parse_rules(&mut words)?,
+            },
+            "REJECT" => PeerMessage::Reject(rest(words)),
+            "MOVE" => PeerMessage::Move {
+                player: Player::from_name(words.next()?)?,
+                mv: Move::from_name(words.next()?)?,
+            },
+            "REMATCH" => PeerMessage::Rematch,
+            "START" => PeerMessage::Start {
+                first: Player::from_name(words.next()?)?,
+            },
+            "RESIGN" => PeerMessage::Resign {
+                player: Player::from_name(words.next()?)?,
+            },
+            "OFFER_DRAW" => PeerMessage::OfferDraw {
+                player: Player::from_name(words.next()?)?,
+            },
+            "ANSWER_DRAW" => PeerMessage::AnswerDraw {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "TAKEBACK" => PeerMessage::RequestTakeback {
+                player: Player::from_name(words.next()?)?,
+            },
+            "ANSWER_TAKEBACK" => PeerMessage::AnswerTakeback {
+                accept: parse_yes_no(words.next()?)?,
+            },
+            "CHAT" => PeerMessage::Chat {
+                text: sanitize_chat(&rest(words)),
+            },
+            "TIMEOUT" => PeerMessage::Timeout {
+                player: Player::from_name(words.next()?)?,
+            },
+            _ => return None,
+        };
+
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Power;
+    use crate::clock::Bonus;
+
+    fn round_trip<M: Message + PartialEq + std::fmt::Debug>(msg: M) {
+        assert_eq!(M::decode(&msg.encode()), Some(msg));
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        let clock = Some(TimeControl::new(5, Bonus::Delay(3)));
+        for msg in [
+            ClientMessage::Hello {
+                version: VERSION,
+                name: "ada".to_string(),
+                token: None,
+            },
+            ClientMessage::Hello {
+                version: VERSION,
+                name: "ada".to_string(),
+                token: Some("00ff00ff00ff00ff".to_string()),
+            },
+            ClientMessage::List,
+            ClientMessage::Create {
+                name: "ada".to_string(),
+                clock,
+            },
+            ClientMessage::Create {
+                name: "ada".to_string(),
+                clock: None,
+            },
+            ClientMessage::Join {
+                code: "ABCD".to_string(),
+            },
+            ClientMessage::Watch {
+                code: "ABCD".to_string(),
+            },
+            ClientMessage::Move { col: 6 },
+            ClientMessage::Rematch,
+            ClientMessage::Leave,
+            ClientMessage::Resign,
+            ClientMessage::OfferDraw,
+            ClientMessage::AnswerDraw { accept: true },
+            ClientMessage::RequestTakeback,
+            ClientMessage::AnswerTakeback { accept: false },
+            ClientMessage::Chat {
+                text: "good game".to_string(),
+            },
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let clock = Some(TimeControl::new(3, Bonus::Increment(2)));
+        let room = RoomInfo {
+            code: "ABCD".to_string(),
+            name: "ada".to_string(),
+            players: 1,
+            spectators: 2,
+            clock,
+        };
+        for msg in [
+            ServerMessage::Welcome {
+                version: VERSION,
+                token: "00ff00ff00ff00ff".to_string(),
+            },
+            ServerMessage::Lobby(vec![]),
+            ServerMessage::Lobby(vec![
+                room.clone(),
+                RoomInfo {
+                    clock: None,
+                    ..room
+                },
+            ]),
+            ServerMessage::Joined {
+                code: "ABCD".to_string(),
+                role: Role::Spectator,
+            },
+            ServerMessage::Start {
+                first: Player::Yellow,
+                clock,
+            },
+            ServerMessage::Moved {
+                player: Player::Red,
+                col: 0,
+            },
+            ServerMessage::Clock {
+                red: 12.5,
+                yellow: 300.0,
+            },
+            ServerMessage::Timeout {
+                player: Player::Red,
+            },
+            ServerMessage::Away {
+                player: Player::Yellow,
+            },
+            ServerMessage::Back {
+                player: Player::Yellow,
+            },
+            ServerMessage::Forfeit {
+                player: Player::Red,
+            },
+            ServerMessage::Left {
+                role: Role::Player(Player::Red),
+            },
+            ServerMessage::Closed,
+            ServerMessage::Resigned {
+                player: Player::Yellow,
+            },
+            ServerMessage::DrawOffered {
+                player: Player::Red,
+            },
+            ServerMessage::DrawAnswered { accept: true },
+            ServerMessage::TakebackRequested {
+                player: Player::Yellow,
+            },
+            ServerMessage::TakebackAnswered { accept: false },
+            ServerMessage::Chat {
+                name: "ada".to_string(),
+                text: "good game".to_string(),
+            },
+            ServerMessage::Error("room is full".to_string()),
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn peer_messages_round_trip() {
+        let rules = Rules {
+            variant: Variant::Cylinder,
+            players: 2,
+            connect: 5,
+            exact: false,
+            board: (9, 7),
+        };
+        for msg in [
+            PeerMessage::Hello {
+                version: VERSION,
+                rules: Some(rules),
+            },
+            PeerMessage::Hello {
+                version: VERSION,
+                rules: None,
+            },
+            PeerMessage::Accept {
+                rules: Rules {
+                    exact: true,
+                    ..rules
+                },
+                guest: Player::Yellow,
+                first: Player::Red,
+                clock: Some(TimeControl::new(1, Bonus::Increment(0))),
+            },
+            PeerMessage::Reject("different version".to_string()),
+            PeerMessage::Move {
+                player: Player::Red,
+                mv: Move::Drop(3),
+            },
+            PeerMessage::Move {
+                player: Player::Yellow,
+                mv: Move::Pop(0),
+            },
+            PeerMessage::Move {
+                player: Player::Red,
+                mv: Move::Power(Power::Bomb, 2),
+            },
+            PeerMessage::Move {
+                player: Player::Yellow,
+                mv: Move::Swap,
+            },
+            PeerMessage::Move {
+                player: Player::Red,
+                mv: Move::Place(9, 7),
+            },
+            PeerMessage::Rematch,
+            PeerMessage::Start {
+                first: Player::Yellow,
+            },
+            PeerMessage::Resign {
+                player: Player::Red,
+            },
+            PeerMessage::OfferDraw {
+                player: Player::Yellow,
+            },
+            PeerMessage::AnswerDraw { accept: false },
+            PeerMessage::RequestTakeback {
+                player: Player::Red,
+            },
+            PeerMessage::AnswerTakeback { accept: true },
+            PeerMessage::Chat {
+                text: "good game".to_string(),
+            },
+            PeerMessage::Timeout {
+                player: Player::Yellow,
+            },
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn sync_keeps_offer_and_outcome() {
+        round_trip(ServerMessage::Sync {
+            first: Player::Yellow,
+            clock: None,
+            offer: Some(Offer::Takeback(Player::Red)),
+            outcome: None,
+            moves: vec![3, 3, 4],
+        });
+        round_trip(ServerMessage::Sync {
+            first: Player::Red,
+            clock: None,
+            offer: None,
+            outcome: Some(Outcome::Resigned(Player::Yellow)),
+            moves: vec![],
+        });
+    }
+
+    #[test]
+    fn unknown_rules_can_still_be_rejected() {
+        let msg = PeerMessage::decode("HELLO 6 gravity 2 4 any 7x6");
+        assert_eq!(
+            msg,
+            Some(PeerMessage::Hello {
+                version: 6,
+                rules: None
+            })
+        );
+    }
+
+    #[test]
+    fn every_outcome_has_a_name() {
+        for player in [Player::Red, Player::Yellow] {
+            for outcome in [
+                Outcome::Connected(player),
+                Outcome::Full,
+                Outcome::Resigned(player),
+                Outcome::AgreedDraw,
+                Outcome::Forfeit(player),
+                Outcome::Timeout(player),
+                Outcome::Repetition,
+                Outcome::Completed(player),
+                Outcome::Outlasted(player),
+            ] {
+                assert_eq!(
+                    parse_outcome(&outcome_name(Some(outcome))),
+                    Some(Some(outcome))
+                );
+            }
+        }
+        assert_eq!(parse_outcome("won:red"), None);
+    }
+
+    #[test]
+    fn names_and_chat_stay_on_one_line() {
+        let msg = ClientMessage::decode("HELLO 6 ada lovelace").unwrap();
+        assert!(matches!(msg, ClientMessage::Hello { name, .. } if name == "ada"));
+        assert_eq!(sanitize_name("a b,c"), "a_b_c");
+        assert_eq!(sanitize_chat(" hi\nthere "), "hi there");
+        assert_eq!(ClientMessage::decode("MOVE left"), None);
+    }
+}
@@ -0,0 +1,134 @@
+// Finished games as plain text, one `key value` line per field, e.g.
+//
+//   variant classic
+//   clock 3+2
+//   first red
+//   seed 8203417
+//   moves 3 3 4 2 p3
+//   result yellow time
+//
+// Moves are columns from 0, pops in PopOut are marked with a `p` and the
+// power-ups with `a` (anvil), `b` (bomb), `w` (wall) or `x` (x2).
+// The result names the winner, or `draw`, and how the game ended. The seed is
+// left out for network games, whose opener is picked by the other side.
+// Games of more than two add `players 3`, a line length other than four adds
+// `connect 5` (`connect 5 exact` when longer lines don't count), a board other
+// than 7 x 6 adds `board 9x9`, and players who resigned along the way are
+// listed as `out green 12`, with the number of moves played before they left.
+// Discs placed with gravity off are column and row, like `3:5`.
+// Games played with the swap rule add `swap on`, and a swap shows up among
+// the moves as `s`.
+
+use crate::board::{Move, Outcome, Player, Variant, COLS, CONNECT, ROWS};
+use crate::clock::{control_name, TimeControl};
+
+pub struct Record {
+    pub variant: Variant,
+    pub clock: Option<TimeControl>,
+    pub first: Player,
+    pub seed: Option<u64>,
+    pub moves: Vec<Move>,
+    pub outcome: Option<Outcome>,
+    pub players: usize,
+    pub connect: usize,
+    pub exact: bool,
+    // columns and rows
+    pub board: (usize, usize),
+    pub out: Vec<(usize, Player)>,
+    pub swap: bool,
+}
+
+fn how(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Connected(_) => "connected",
+        Outcome::Full => "full",
+        Outcome::Resigned(_) => "resigned",
+        Outcome::AgreedDraw => "agreed",
+        Outcome::Forfeit(_) => "forfeit",
+        Outcome::Timeout(_) => "time",
+        Outcome::Repetition => "repetition",
+        Outcome::Completed(_) => "four",
+        Outcome::Outlasted(_) => "outlasted",
+    }
+}
+
+impl Record {
+    pub fn encode(&self) -> String {
+        let moves: Vec<String> = self.moves.iter().map(|mv| mv.name()).collect();
+        let result = match self.outcome {
+            Some(outcome) => {
+                let winner = outcome.winner().map_or("draw", |winner| winner.name());
+                format!("{} {}", winner, how(outcome))
+            }
+            None => "unfinished".to_string(),
+        };
+
+        let mut extra = self
+            .seed
+            .map_or(String::new(), |seed| format!("seed {}\n", seed));
+        if self.players != 2 {
+            extra += &format!("players {}\n", self.players);
+        }
+        if self.connect != CONNECT || self.exact {
+            let exact = if self.exact { " exact" } else { "" };
+            extra += &format!("connect {}{}\n", self.connect, exact);
+        }
+        if self.board != (COLS, ROWS) {
+            extra += &format!("board {}x{}\n", self.board.0, self.board.1);
+        }
+        if self.swap {
+            extra += "swap on\n";
+        }
+        for (ply, player) in &self.out {
+            extra += &format!("out {} {}\n", player.name(), ply);
+        }
+        format!(
+            "variant {}\nclock {}\nfirst {}\n{}moves {}\nresult {}\n",
+            self.variant.name(),
+            control_name(self.clock),
+            self.first.name(),
+            extra,
+            moves.join(" "),
+            result
+        )
+    }
+
+    // writes the record to a new file in `games/` and returns its path
+    pub fn save(&self) -> std::io::Result<std::path::PathBuf> {
+        save_file("connect4", &self.encode())
+    }
+}
+
+// writes `text` to `games/<kind>-<time>.txt` and returns that path, files
+// saved in the same second get `-2`, `-3`, ... so none is overwritten
+pub fn save_file(kind: &str, text: &str) -> std::io::Result<std::path::PathBuf> {
+    use std::io::{ErrorKind, Write};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let dir = std::path::Path::new("games");
+    std::fs::create_dir_all(dir)?;
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let mut n = 1;
+    loop {
+        let name = match n {
+            1 => format!("{}-{}.txt", kind, secs),
+            n => format!("{}-{}-{}.txt", kind, secs, n),
+        };
+        let path = dir.join(name);
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path);
+        match file {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Screens of the app, kept as a stack. Only the scene on top is updated;
+// pause, settings, results and the match summary are drawn over the game
+// below them, so going back is just popping the stack.
+
+use crate::menu::List;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Scene {
+    Menu(List),
+    Game,
+    Pause(List),
+    Settings(List),
+    // names and disc colours, reached from the settings
+    Names(List),
+    Results,
+    // the end of a match, in place of the results of its last game
+    Summary,
+}
+
+impl Scene {
+    // scenes drawn over whatever is under them
+    pub fn is_overlay(self) -> bool {
+        matches!(
+            self,
+            Scene::Pause(_)
+                | Scene::Settings(_)
+                | Scene::Names(_)
+                | Scene::Results
+                | Scene::Summary
+        )
+    }
+}
+
+pub struct Scenes {
+    stack: Vec<Scene>,
+}
+
+impl Scenes {
+    pub fn new(first: Scene) -> Self {
+        Scenes { stack: vec![first] }
+    }
+
+    pub fn top(&self) -> Scene {
+        // the root scene is never popped
+        self.stack[self.stack.len() - 1]
+    }
+
+    // hands back the changed list of the menu on top
+    pub fn set_top(&mut self, scene: Scene) {
+        let last = self.stack.len() - 1;
+        self.stack[last] = scene;
+    }
+
+    pub fn push(&mut self, scene: Scene) {
+        self.stack.push(scene);
+    }
+
+    pub fn pop(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    pub fn has_game(&self) -> bool {
+        self.stack.contains(&Scene::Game)
+    }
+
+    // back to the main menu, which is always at the bottom
+    pub fn back_to_menu(&mut self) {
+        self.stack.truncate(1);
+    }
+}
@@ -0,0 +1,851 @@
+// Headless game server: hosts named rooms, keeps the lobby list and streams
+// moves to the players and spectators of every room.
+// Each connection gets a reader thread, the game state itself lives in the
+// thread that runs `Server::handle`, so no locking is needed.
+// A player that drops mid-game keeps their seat for `GRACE_PERIOD` and can
+// take it back by saying hello with their session token, after that the
+// game is scored as a forfeit.
+// Rooms with a time control keep the clocks here too, players are told the
+// time left after every move and the server calls the time out.
+// Writes go through a bounded queue per client drained by its own writer
+// thread, a peer that stops reading is dropped once its queue is full.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notan::prelude::*;
+
+use crate::board::{
+    check_winner, coin_flip, drop_piece, is_full, lift_piece, Offer, Outcome, Player, Table,
+};
+use crate::clock::{Clock, TimeControl};
+use crate::protocol::{ClientMessage, Message, Role, RoomInfo, ServerMessage, VERSION};
+
+type ClientId = usize;
+
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const GRACE_PERIOD: Duration = Duration::from_secs(60);
+// lines waiting for a slow client before it is dropped
+const OUTBOX: usize = 256;
+const TICK: Duration = Duration::from_millis(500);
+
+enum Event {
+    Connected(ClientId, TcpStream, SyncSender<String>),
+    Message(ClientId, ClientMessage),
+    Invalid(ClientId),
+    Disconnected(ClientId),
+}
+
+struct Client {
+    // kept to shut a stalled connection down
+    stream: TcpStream,
+    outbox: SyncSender<String>,
+    name: Option<String>,
+    token: String,
+    room: Option<String>,
+}
+
+// seat kept for a player that lost the connection
+struct Away {
+    token: String,
+    since: Instant,
+}
+
+struct Room {
+    name: String,
+    seats: [Option<ClientId>; 2],
+    away: [Option<Away>; 2],
+    spectators: Vec<ClientId>,
+    table: Table,
+    first: Player,
+    turn: Player,
+    moves: Vec<usize>,
+    offer: Option<Offer>,
+    control: Option<TimeControl>,
+    clock: Option<Clock>,
+    started: bool,
+    over: bool,
+    // endings the moves don't show: resignations, forfeits, time outs and draws
+    outcome: Option<Outcome>,
+}
+
+impl Room {
+    fn new(name: String, control: Option<TimeControl>) -> Self {
+        Room {
+            name,
+            seats: [None, None],
+            away: [None, None],
+            spectators: vec![],
+            table: Default::default(),
+            first: Player::Red,
+            turn: Player::Red,
+            moves: vec![],
+            offer: None,
+            control,
+            clock: None,
+            started: false,
+            over: false,
+            outcome: None,
+        }
+    }
+
+    fn start(&mut self, first: Player) {
+        self.table = Default::default();
+        self.first = first;
+        self.turn = first;
+        self.moves.clear();
+        self.offer = None;
+        self.clock = self.control.map(Clock::new);
+        self.started = true;
+        self.over = false;
+        self.outcome = None;
+    }
+
+    fn end(&mut self, outcome: Outcome) {
+        self.over = true;
+        self.offer = None;
+        self.outcome = Some(outcome);
+    }
+
+    fn in_progress(&self) -> bool {
+        self.started && !self.over
+    }
+
+    fn is_free(&self, player: Player) -> bool {
+        self.seats[seat(player)].is_none() && self.away[seat(player)].is_none()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_free(Player::Red) && self.is_free(Player::Yellow)
+    }
+
+    // the game so far and the time left, for players and spectators coming in
+    fn sync(&self) -> Vec<ServerMessage> {
+        let sync = ServerMessage::Sync {
+            first: self.first,
+            clock: self.control,
+            offer: self.offer,
+            outcome: self.outcome,
+            moves: self.moves.clone(),
+        };
+
+        std::iter::once(sync).chain(self.time_left()).collect()
+    }
+
+    fn time_left(&self) -> Option<ServerMessage> {
+        self.clock.map(|clock| ServerMessage::Clock {
+            red: clock.left(Player::Red),
+            yellow: clock.left(Player::Yellow),
+        })
+    }
+
+    fn role_of(&self, id: ClientId) -> Option<Role> {
+        if self.seats[seat(Player::Red)] == Some(id) {
+            Some(Role::Player(Player::Red))
+        } else if self.seats[seat(Player::Yellow)] == Some(id) {
+            Some(Role::Player(Player::Yellow))
+        } else if self.spectators.contains(&id) {
+            Some(Role::Spectator)
+        } else {
+            None
+        }
+    }
+
+    fn members(&self) -> Vec<ClientId> {
+        self.seats
+            .iter()
+            .flatten()
+            .chain(self.spectators.iter())
+            .copied()
+            .collect()
+    }
+
+    fn info(&self, code: &str) -> RoomInfo {
+        RoomInfo {
+            code: code.to_string(),
+            name: self.name.clone(),
+            players: [Player::Red, Player::Yellow]
+                .into_iter()
+                .filter(|&player| !self.is_free(player))
+                .count(),
+            spectators: self.spectators.len(),
+            clock: self.control,
+        }
+    }
+}
+
+// only red and yellow play on the server
+fn seat(player: Player) -> usize {
+    player.index()
+}
+
+// session tokens take over a seat, so they come from the OS and not the
+// seeded generator that picks room codes and openers
+fn new_token() -> String {
+    let mut bytes = [0; 16];
+    getrandom::getrandom(&mut bytes).expect("no randomness from the OS");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn run(port: u16) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("connect4 server listening on port {}", port);
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || accept(listener, tx));
+
+    let mut server = Server::default();
+    let mut ticked = Instant::now();
+    loop {
+        let event = match rx.recv_timeout(TICK) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+
+        // time up to now belongs to the player on turn before this event
+        server.run_clocks(ticked.elapsed().as_secs_f32());
+        ticked = Instant::now();
+
+        if let Some(event) = event {
+            server.handle(event);
+        }
+        server.expire_away();
+    }
+}
+
+fn accept(listener: TcpListener, tx: Sender<Event>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        let (Ok(handle), Ok(writer)) = (stream.try_clone(), stream.try_clone()) else {
+            continue;
+        };
+
+        let (outbox, queued) = mpsc::sync_channel(OUTBOX);
+        thread::spawn(move || write_lines(writer, queued));
+
+        if tx.send(Event::Connected(id, handle, outbox)).is_err() {
+            return;
+        }
+
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                let event = match ClientMessage::decode(&line) {
+                    Some(msg) => Event::Message(id, msg),
+                    None => Event::Invalid(id),
+                };
+
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+
+            let _ = tx.send(Event::Disconnected(id));
+        });
+    }
+}
+
+// sends queued lines until the client is gone or stops taking them
+fn write_lines(mut stream: TcpStream, queued: mpsc::Receiver<String>) {
+    for line in queued {
+        if writeln!(stream, "{}", line).is_err() {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    rng: Random,
+    clients: HashMap<ClientId, Client>,
+    rooms: BTreeMap<String, Room>,
+}
+
+impl Server {
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, stream, outbox) => {
+                self.clients.insert(
+                    id,
+                    Client {
+                        stream,
+                        outbox,
+                        name: None,
+                        token: String::new(),
+                        room: None,
+                    },
+                );
+            }
+            Event::Message(id, msg) => self.on_message(id, msg),
+            Event::Invalid(id) => {
+                self.send(id, &ServerMessage::Error("unknown message".to_string()));
+            }
+            Event::Disconnected(id) => {
+                self.disconnect(id);
+                self.clients.remove(&id);
+            }
+        }
+    }
+
+    fn on_message(&mut self, id: ClientId, msg: ClientMessage) {
+        let Some(client) = self.clients.get(&id) else {
+            return;
+        };
+
+        if let ClientMessage::Hello {
+            version,
+            name,
+            token,
+        } = msg
+        {
+            if version != VERSION {
+                let text = format!("server speaks version {}", VERSION);
+                self.send(id, &ServerMessage::Error(text));
+                return;
+            }
+
+            self.hello(id, name, token);
+            return;
+        }
+
+        if client.name.is_none() {
+            self.send(id, &ServerMessage::Error("say hello first".to_string()));
+            return;
+        }
+
+        match msg {
+            ClientMessage::Hello { .. } => {}
+            ClientMessage::List => self.send_lobby(id),
+            ClientMessage::Create { name, clock } => self.create_room(id, name, clock),
+            ClientMessage::Join { code } => self.join_room(id, code),
+            ClientMessage::Watch { code } => self.watch_room(id, code),
+            ClientMessage::Move { col } => self.play(id, col),
+            ClientMessage::Rematch => self.rematch(id),
+            ClientMessage::Leave => self.leave_room(id),
+            ClientMessage::Resign => self.resign(id),
+            ClientMessage::OfferDraw => self.offer(id, Offer::Draw),
+            ClientMessage::AnswerDraw { accept } => self.answer(id, accept),
+            ClientMessage::RequestTakeback => self.offer(id, Offer::Takeback),
+            ClientMessage::AnswerTakeback { accept } => self.answer(id, accept),
+            ClientMessage::Chat { text } => self.chat(id, text),
+        }
+    }
+
+    fn hello(&mut self, id: ClientId, name: String, token: Option<String>) {
+        let resumed = token.and_then(|token| {
+            self.rooms.iter().find_map(|(code, room)| {
+                let player = [Player::Red, Player::Yellow].into_iter().find(
+                    |&player| matches!(&room.away[seat(player)], Some(away) if away.token == token),
+                )?;
+                Some((code.clone(), player, token.clone()))
+            })
+        });
+
+        let token = match &resumed {
+            Some((_, _, token)) => token.clone(),
+            None => new_token(),
+        };
+
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.name = Some(name);
+            client.token = token.clone();
+        }
+
+        self.send(
+            id,
+            &ServerMessage::Welcome {
+                version: VERSION,
+                token,
+            },
+        );
+
+        let Some((code, player, _)) = resumed else {
+            self.send_lobby(id);
+            return;
+        };
+
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+        room.away[seat(player)] = None;
+        room.seats[seat(player)] = Some(id);
+        let sync = room.sync();
+        self.set_room(id, Some(code.clone()));
+
+        let role = Role::Player(player);
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                code: code.clone(),
+                role,
+            },
+        );
+        for msg in sync {
+            self.send(id, &msg);
+        }
+        self.broadcast(&code, &ServerMessage::Back { player });
+    }
+
+    fn create_room(&mut self, id: ClientId, name: String, clock: Option<TimeControl>) {
+        self.leave_room(id);
+
+        let code = self.new_code();
+        let mut room = Room::new(name, clock);
+        room.seats[seat(Player::Red)] = Some(id);
+        self.rooms.insert(code.clone(), room);
+        self.set_room(id, Some(code.clone()));
+
+        let role = Role::Player(Player::Red);
+        self.send(id, &ServerMessage::Joined { code, role });
+        self.broadcast_lobby();
+    }
+
+    fn join_room(&mut self, id: ClientId, code: String) {
+        let Some(room) = self.rooms.get(&code) else {
+            self.send(id, &ServerMessage::Error("no such room".to_string()));
+            return;
+        };
+
+        if room.role_of(id).is_some() {
+            self.send(
+                id,
+                &ServerMessage::Error("already in this room".to_string()),
+            );
+            return;
+        }
+
+        let Some(free) = [Player::Red, Player::Yellow]
+            .into_iter()
+            .find(|&player| room.is_free(player))
+        else {
+            self.send(id, &ServerMessage::Error("room is full".to_string()));
+            return;
+        };
+
+        self.leave_room(id);
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+        room.seats[seat(free)] = Some(id);
+        let ready = room.seats.iter().all(|seat| seat.is_some());
+        self.set_room(id, Some(code.clone()));
+
+        let role = Role::Player(free);
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                code: code.clone(),
+                role,
+            },
+        );
+
+        if ready {
+            let first = coin_flip(&mut self.rng);
+
+            if let Some(room) = self.rooms.get_mut(&code) {
+                room.start(first);
+                let clock = room.control;
+                self.broadcast(&code, &ServerMessage::Start { first, clock });
+            }
+        }
+
+        self.broadcast_lobby();
+    }
+
+    fn watch_room(&mut self, id: ClientId, code: String) {
+        let Some(room) = self.rooms.get(&code) else {
+            self.send(id, &ServerMessage::Error("no such room".to_string()));
+            return;
+        };
+
+        if room.role_of(id).is_some() {
+            self.send(
+                id,
+                &ServerMessage::Error("already in this room".to_string()),
+            );
+            return;
+        }
+
+        self.leave_room(id);
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+        room.spectators.push(id);
+
+        // late joiners get everything played so far
+        let sync = if room.started { room.sync() } else { vec![] };
+
+        self.set_room(id, Some(code.clone()));
+        let role = Role::Spectator;
+        self.send(id, &ServerMessage::Joined { code, role });
+        for msg in sync {
+            self.send(id, &msg);
+        }
+
+        self.broadcast_lobby();
+    }
+
+    fn play(&mut self, id: ClientId, col: usize) {
+        let Some(code) = self.room_of(id) else {
+            return;
+        };
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+
+        let player = room.turn;
+        if !room.in_progress() || room.seats[seat(player)] != Some(id) {
+            self.send(id, &ServerMessage::Error("not your turn".to_string()));
+            return;
+        }
+
+        if room.offer.is_some() {
+            let text = "answer the pending offer first".to_string();
+            self.send(id, &ServerMessage::Error(text));
+            return;
+        }
+
+        if drop_piece(&mut room.table, col, player).is_none() {
+            self.send(id, &ServerMessage::Error("column is full".to_string()));
+            return;
+        }
+
+        room.moves.push(col);
+        room.turn = player.other();
+        room.over = check_winner(&room.table).is_some() || is_full(&room.table);
+        if let Some(clock) = &mut room.clock {
+            clock.moved(player);
+        }
+        let time_left = room.time_left();
+
+        self.broadcast(&code, &ServerMessage::Moved { player, col });
+        if let Some(msg) = time_left {
+            self.broadcast(&code, &msg);
+        }
+    }
+
+    // room and colour of a player in a running game
+    fn playing(&self, id: ClientId) -> Option<(String, Player)> {
+        let code = self.room_of(id)?;
+        let room = self.rooms.get(&code)?;
+        match room.role_of(id)? {
+            Role::Player(player) if room.in_progress() => Some((code, player)),
+            _ => None,
+        }
+    }
+
+    fn resign(&mut self, id: ClientId) {
+        let Some((code, player)) = self.playing(id) else {
+            return;
+        };
+
+        if let Some(room) = self.rooms.get_mut(&code) {
+            room.end(Outcome::Resigned(player));
+        }
+        self.broadcast(&code, &ServerMessage::Resigned { player });
+    }
+
+    fn offer(&mut self, id: ClientId, offer: fn(Player) -> Offer) {
+        let Some((code, player)) = self.playing(id) else {
+            return;
+        };
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+
+        let offer = offer(player);
+        // only the player that moved last can take it back
+        let can_take_back = !room.moves.is_empty() && room.turn != player;
+        if room.offer.is_some() || (matches!(offer, Offer::Takeback(_)) && !can_take_back) {
+            self.send(
+                id,
+                &ServerMessage::Error("can't ask for that now".to_string()),
+            );
+            return;
+        }
+
+        room.offer = Some(offer);
+        let msg = match offer {
+            Offer::Draw(player) => ServerMessage::DrawOffered { player },
+            Offer::Takeback(player) => ServerMessage::TakebackRequested { player },
+        };
+        self.broadcast(&code, &msg);
+    }
+
+    fn answer(&mut self, id: ClientId, accept: bool) {
+        let Some((code, player)) = self.playing(id) else {
+            return;
+        };
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+
+        // offers are answered by the other player
+        let Some(offer) = room.offer.filter(|offer| offer.by() != player) else {
+            return;
+        };
+        room.offer = None;
+
+        let msg = match offer {
+            Offer::Draw(_) => {
+                if accept {
+                    room.end(Outcome::AgreedDraw);
+                }
+                ServerMessage::DrawAnswered { accept }
+            }
+            Offer::Takeback(_) => {
+                if accept {
+                    if let Some(col) = room.moves.pop() {
+                        if let Some((_, player)) = lift_piece(&mut room.table, col) {
+                            room.turn = player;
+                        }
+                    }
+                }
+                ServerMessage::TakebackAnswered { accept }
+            }
+        };
+        self.broadcast(&code, &msg);
+    }
+
+    fn chat(&mut self, id: ClientId, text: String) {
+        let Some(code) = self.room_of(id) else {
+            return;
+        };
+        let Some(name) = self.clients.get(&id).and_then(|client| client.name.clone()) else {
+            return;
+        };
+
+        if !text.is_empty() {
+            self.broadcast(&code, &ServerMessage::Chat { name, text });
+        }
+    }
+
+    fn rematch(&mut self, id: ClientId) {
+        let Some(code) = self.room_of(id) else {
+            return;
+        };
+
+        let first = coin_flip(&mut self.rng);
+
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+        let seated = matches!(room.role_of(id), Some(Role::Player(_)));
+        let ready = room.seats.iter().all(|seat| seat.is_some());
+        if !room.over || !seated || !ready {
+            return;
+        }
+
+        room.start(first);
+        let clock = room.control;
+        self.broadcast(&code, &ServerMessage::Start { first, clock });
+    }
+
+    fn leave_room(&mut self, id: ClientId) {
+        let Some(code) = self.room_of(id) else {
+            return;
+        };
+        self.set_room(id, None);
+
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+
+        let Some(role) = room.role_of(id) else {
+            return;
+        };
+
+        match role {
+            Role::Player(player) => {
+                room.seats[seat(player)] = None;
+                // leaving a running game gives it away
+                if room.in_progress() {
+                    room.end(Outcome::Forfeit(player));
+                    self.broadcast(&code, &ServerMessage::Forfeit { player });
+                }
+            }
+            Role::Spectator => room.spectators.retain(|&other| other != id),
+        }
+
+        self.broadcast(&code, &ServerMessage::Left { role });
+        self.close_if_empty(&code);
+        self.broadcast_lobby();
+    }
+
+    // a dropped player keeps the seat of a running game for a while
+    fn disconnect(&mut self, id: ClientId) {
+        let Some(code) = self.room_of(id) else {
+            return;
+        };
+        let Some(room) = self.rooms.get_mut(&code) else {
+            return;
+        };
+        let Some(Role::Player(player)) = room.role_of(id) else {
+            self.leave_room(id);
+            return;
+        };
+        if !room.in_progress() {
+            self.leave_room(id);
+            return;
+        }
+
+        let token = self.clients[&id].token.clone();
+        room.seats[seat(player)] = None;
+        room.away[seat(player)] = Some(Away {
+            token,
+            since: Instant::now(),
+        });
+
+        self.set_room(id, None);
+        self.broadcast(&code, &ServerMessage::Away { player });
+    }
+
+    // scores a forfeit for every player that did not come back in time
+    // clocks start with the first move and keep running while a player is away
+    fn run_clocks(&mut self, delta: f32) {
+        let mut flagged = vec![];
+        for (code, room) in self.rooms.iter_mut() {
+            if !room.in_progress() || room.moves.is_empty() {
+                continue;
+            }
+
+            let turn = room.turn;
+            if room
+                .clock
+                .as_mut()
+                .is_some_and(|clock| clock.tick(turn, delta))
+            {
+                room.end(Outcome::Timeout(turn));
+                flagged.push((code.clone(), turn));
+            }
+        }
+
+        for (code, player) in flagged {
+            self.broadcast(&code, &ServerMessage::Timeout { player });
+        }
+    }
+
+    fn expire_away(&mut self) {
+        let mut expired = vec![];
+        for (code, room) in self.rooms.iter_mut() {
+            for player in [Player::Red, Player::Yellow] {
+                let away = &mut room.away[seat(player)];
+                if away
+                    .as_ref()
+                    .is_some_and(|away| away.since.elapsed() >= GRACE_PERIOD)
+                {
+                    *away = None;
+                    let forfeit = room.in_progress();
+                    if forfeit {
+                        room.end(Outcome::Forfeit(player));
+                    }
+                    room.over = true;
+                    expired.push((code.clone(), player, forfeit));
+                }
+            }
+        }
+
+        for (code, player, forfeit) in expired {
+            if forfeit {
+                self.broadcast(&code, &ServerMessage::Forfeit { player });
+            }
+            let role = Role::Player(player);
+            self.broadcast(&code, &ServerMessage::Left { role });
+            self.close_if_empty(&code);
+            self.broadcast_lobby();
+        }
+    }
+
+    fn close_if_empty(&mut self, code: &str) {
+        if !self.rooms.get(code).is_some_and(|room| room.is_empty()) {
+            return;
+        }
+
+        if let Some(room) = self.rooms.remove(code) {
+            for other in room.spectators {
+                self.set_room(other, None);
+                self.send(other, &ServerMessage::Closed);
+            }
+        }
+    }
+
+    fn new_code(&mut self) -> String {
+        loop {
+            let code: String = (0..4)
+                .map(|_| CODE_LETTERS[self.rng.gen_range(0..CODE_LETTERS.len())] as char)
+                .collect();
+
+            if !self.rooms.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    fn room_of(&self, id: ClientId) -> Option<String> {
+        self.clients.get(&id)?.room.clone()
+    }
+
+    fn set_room(&mut self, id: ClientId, room: Option<String>) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.room = room;
+        }
+    }
+
+    fn send(&mut self, id: ClientId, msg: &ServerMessage) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            // a broken or stalled connection is cleaned up by its reader thread
+            if let Err(TrySendError::Full(_)) = client.outbox.try_send(msg.encode()) {
+                let _ = client.stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    fn broadcast(&mut self, code: &str, msg: &ServerMessage) {
+        let members = self
+            .rooms
+            .get(code)
+            .map(|room| room.members())
+            .unwrap_or_default();
+
+        for id in members {
+            self.send(id, msg);
+        }
+    }
+
+    fn send_lobby(&mut self, id: ClientId) {
+        let rooms = self
+            .rooms
+            .iter()
+            .map(|(code, room)| room.info(code))
+            .collect();
+
+        self.send(id, &ServerMessage::Lobby(rooms));
+    }
+
+    // keeps the lobby list of everyone that is not in a room up to date
+    fn broadcast_lobby(&mut self) {
+        let idle: Vec<ClientId> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.name.is_some() && client.room.is_none())
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in idle {
+            self.send_lobby(id);
+        }
+    }
+}
@@ -0,0 +1,567 @@
+// User settings, kept between runs. On the desktop they are a small TOML
+// file in the OS config directory, e.g. `~/.config/connect4/settings.toml`,
+// on the web build they go to the browser's `localStorage` in the same format.
+// Only flat `key = value` lines are read, anything unknown is skipped and
+// missing or broken values fall back to their defaults.
+// Every file carries its `version`; when a field changes, VERSION goes up and
+// a step in MIGRATIONS rewrites the keys of older files.
+
+use std::collections::HashMap;
+
+use notan::prelude::Color;
+
+use crate::board::Player;
+use crate::clock::{control_name, TimeControl};
+use crate::match_play::Order;
+use crate::menu::Mode;
+use crate::theme::{Pattern, Style, Theme, CLASSIC, CUSTOM};
+
+type Values = HashMap<String, String>;
+
+// steps from each version to the next, the first one upgrades version 1 files
+const MIGRATIONS: &[fn(&mut Values)] = &[custom_colors, sound_to_muted];
+const VERSION: usize = MIGRATIONS.len() + 1;
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "connect4.settings";
+
+// how fast discs fall
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Animation {
+    Slow,
+    Normal,
+    Fast,
+    Off,
+}
+
+impl Animation {
+    const ALL: [Animation; 4] = [
+        Animation::Slow,
+        Animation::Normal,
+        Animation::Fast,
+        Animation::Off,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Animation::Slow => "slow",
+            Animation::Normal => "normal",
+            Animation::Fast => "fast",
+            Animation::Off => "off",
+        }
+    }
+
+    // multiplies the frame time, discs land at once when off
+    pub fn speed(self) -> f32 {
+        match self {
+            Animation::Slow => 0.5,
+            Animation::Normal => 1.0,
+            Animation::Fast => 2.0,
+            Animation::Off => 0.0,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Animation> {
+        Animation::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn next(self) -> Animation {
+        next_of(&Animation::ALL, self)
+    }
+}
+
+// who moves first in a new hot seat game
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Starter {
+    Random,
+    Red,
+    Yellow,
+    // the one that didn't open the last game
+    Alternate,
+    // the loser of the last game, draws alternate
+    LoserFirst,
+}
+
+impl Starter {
+    const ALL: [Starter; 5] = [
+        Starter::Random,
+        Starter::Red,
+        Starter::Yellow,
+        Starter::Alternate,
+        Starter::LoserFirst,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Starter::Random => "random",
+            Starter::Red => "red",
+            Starter::Yellow => "yellow",
+            Starter::Alternate => "alternate",
+            Starter::LoserFirst => "loser first",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Starter> {
+        Starter::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub fn next(self) -> Starter {
+        next_of(&Starter::ALL, self)
+    }
+}
+
+fn next_of<T: Copy + PartialEq>(all: &[T], value: T) -> T {
+    let i = all.iter().position(|&v| v == value).unwrap_or(0);
+    all[(i + 1) % all.len()]
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub red_name: String,
+    pub yellow_name: String,
+    // the third and fourth players of bigger hot seat games
+    pub green_name: String,
+    pub purple_name: String,
+    // disc colours of the custom theme
+    pub red_color: Color,
+    pub yellow_color: Color,
+    pub theme: Theme,
+    pub pattern: Pattern,
+    // the mode the main menu starts on
+    pub opponent: Mode,
+    pub clock: Option<TimeControl>,
+    pub animation: Animation,
+    // percent of full loudness, muting keeps it for later
+    pub volume: u8,
+    pub muted: bool,
+    pub starter: Starter,
+    // after the first disc the second player may take it over
+    pub swap: bool,
+    // who opens the later games of a match
+    pub match_order: Order,
+    // fixed seed for the random choices, a new one every run if none
+    pub seed: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            red_name: Player::Red.name().to_string(),
+            yellow_name: Player::Yellow.name().to_string(),
+            green_name: Player::Green.name().to_string(),
+            purple_name: Player::Purple.name().to_string(),
+            red_color: Color::RED,
+            yellow_color: Color::YELLOW,
+            theme: CLASSIC,
+            pattern: Pattern::Plain,
+            opponent: Mode::HotSeat,
+            clock: None,
+            animation: Animation::Normal,
+            volume: 80,
+            muted: false,
+            starter: Starter::Random,
+            swap: false,
+            match_order: Order::Alternate,
+            seed: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn name(&self, player: Player) -> &str {
+        match player {
+            Player::Red => &self.red_name,
+            Player::Yellow => &self.yellow_name,
+            Player::Green => &self.green_name,
+            Player::Purple => &self.purple_name,
+        }
+    }
+
+    pub fn set_name(&mut self, player: Player, name: String) {
+        match player {
+            Player::Red => self.red_name = name,
+            Player::Yellow => self.yellow_name = name,
+            Player::Green => self.green_name = name,
+            Player::Purple => self.purple_name = name,
+        }
+    }
+
+    pub fn style(&self) -> Style {
+        let mut theme = self.theme;
+        if theme.name == CUSTOM.name {
+            theme.red = self.red_color;
+            theme.yellow = self.yellow_color;
+        }
+
+        Style {
+            theme,
+            pattern: self.pattern,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let lines = [
+            format!("version = {}", VERSION),
+            format!("red_name = {}", quote(&self.red_name)),
+            format!("yellow_name = {}", quote(&self.yellow_name)),
+            format!("green_name = {}", quote(&self.green_name)),
+            format!("purple_name = {}", quote(&self.purple_name)),
+            format!("red_color = {}", quote(&color_name(self.red_color))),
+            format!("yellow_color = {}", quote(&color_name(self.yellow_color))),
+            format!("theme = {}", quote(self.theme.name)),
+            format!("pattern = {}", quote(self.pattern.name())),
+            format!("opponent = {}", quote(self.opponent.name())),
+            format!("clock = {}", quote(&control_name(self.clock))),
+            format!("animation = {}", quote(self.animation.name())),
+            format!("volume = {}", self.volume),
+            format!("muted = {}", self.muted),
+            format!("starter = {}", quote(self.starter.name())),
+            format!("swap = {}", self.swap),
+            format!("match_order = {}", quote(self.match_order.name())),
+            match self.seed {
+                Some(seed) => format!("seed = {}", seed),
+                None => format!("seed = {}", quote("random")),
+            },
+        ];
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn decode(text: &str) -> Settings {
+        let mut values = parse(text);
+        let version = values
+            .get("version")
+            .and_then(|version| version.parse().ok())
+            .unwrap_or(1);
+
+        if version > VERSION {
+            eprintln!("settings are from a newer version, reading what is known");
+        }
+        for step in MIGRATIONS.iter().skip(version.saturating_sub(1)) {
+            step(&mut values);
+        }
+
+        let mut settings = Settings::default();
+        let get = |key: &str| values.get(key).map(String::as_str);
+
+        if let Some(name) = get("red_name").filter(|name| !name.is_empty()) {
+            settings.red_name = name.to_string();
+        }
+        if let Some(name) = get("yellow_name").filter(|name| !name.is_empty()) {
+            settings.yellow_name = name.to_string();
+        }
+        if let Some(name) = get("green_name").filter(|name| !name.is_empty()) {
+            settings.green_name = name.to_string();
+        }
+        if let Some(name) = get("purple_name").filter(|name| !name.is_empty()) {
+            settings.purple_name = name.to_string();
+        }
+        if let Some(color) = get("red_color").and_then(parse_color) {
+            settings.red_color = color;
+        }
+        if let Some(color) = get("yellow_color").and_then(parse_color) {
+            settings.yellow_color = color;
+        }
+        if let Some(theme) = get("theme").and_then(Theme::from_name) {
+            settings.theme = theme;
+        }
+        if let Some(pattern) = get("pattern").and_then(Pattern::from_name) {
+            settings.pattern = pattern;
+        }
+        if let Some(mode) = get("opponent").and_then(Mode::from_name) {
+            settings.opponent = mode;
+        }
+        if let Some(clock) = get("clock") {
+            settings.clock = TimeControl::from_name(clock);
+        }
+        if let Some(animation) = get("animation").and_then(Animation::from_name) {
+            settings.animation = animation;
+        }
+        if let Some(volume) = get("volume").and_then(|volume| volume.parse::<u8>().ok()) {
+            settings.volume = volume.min(100);
+        }
+        if let Some(muted) = get("muted").and_then(|muted| muted.parse().ok()) {
+            settings.muted = muted;
+        }
+        if let Some(starter) = get("starter").and_then(Starter::from_name) {
+            settings.starter = starter;
+        }
+        if let Some(swap) = get("swap").and_then(|swap| swap.parse().ok()) {
+            settings.swap = swap;
+        }
+        if let Some(order) = get("match_order").and_then(Order::from_name) {
+            settings.match_order = order;
+        }
+        if let Some(seed) = get("seed") {
+            settings.seed = seed.parse().ok();
+        }
+
+        settings
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Settings {
+        let Some(path) = path() else {
+            return Settings::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Settings::decode(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+            Err(e) => {
+                eprintln!("could not read {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        let Some(path) = path() else {
+            return;
+        };
+
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, self.encode()));
+        if let Err(e) = saved {
+            eprintln!("could not save the settings to {}: {}", path.display(), e);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Settings {
+        storage()
+            .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+            .map_or_else(Settings::default, |text| Settings::decode(&text))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {
+        if let Some(storage) = storage() {
+            let _ = storage.set_item(STORAGE_KEY, &self.encode());
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// `settings.toml` in the config directory of the OS
+#[cfg(not(target_arch = "wasm32"))]
+fn path() -> Option<std::path::PathBuf> {
+    use std::env::var_os;
+    use std::path::PathBuf;
+
+    let dir = if cfg!(windows) {
+        PathBuf::from(var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(var_os("HOME")?).join("Library/Application Support")
+    } else if let Some(dir) = var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else {
+        PathBuf::from(var_os("HOME")?).join(".config")
+    };
+
+    Some(dir.join("connect4").join("settings.toml"))
+}
+
+// version 2 added themes, colours picked in version 1 go on in the custom one
+fn custom_colors(values: &mut Values) {
+    let defaults = [("red_color", Color::RED), ("yellow_color", Color::YELLOW)];
+    let changed = defaults.iter().any(|&(key, default)| {
+        values
+            .get(key)
+            .and_then(|name| parse_color(name))
+            .is_some_and(|color| color != default)
+    });
+
+    if changed {
+        values.insert("theme".to_string(), CUSTOM.name.to_string());
+    }
+}
+
+// version 3 split sound on and off into a volume and muting
+fn sound_to_muted(values: &mut Values) {
+    if let Some(sound) = values.remove("sound") {
+        values.insert("muted".to_string(), (sound == "false").to_string());
+    }
+}
+
+// reads `key = value` lines, strings lose their quotes and escapes
+fn parse(text: &str) -> Values {
+    let mut values = Values::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let value = value.trim();
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            // a comment can follow a bare value
+            None => value
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        };
+        values.insert(key.trim().to_string(), value);
+    }
+
+    values
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+// the text after the opening quote up to the closing one
+fn unquote(text: &str) -> String {
+    let mut value = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    value
+}
+
+// colours are written like in CSS, `#ff0000`
+pub fn color_name(color: Color) -> String {
+    let [r, g, b, _] = color.rgba_u8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn parse_color(name: &str) -> Option<Color> {
+    let hex = name.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Some(Color::from_bytes(r, g, b, 255))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Bonus;
+    use crate::theme::HIGH_CONTRAST;
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = Settings {
+            red_name: "Ada \"the\" \\ best".to_string(),
+            yellow_name: "tab\there".to_string(),
+            green_name: "two\nlines".to_string(),
+            red_color: Color::from_bytes(0x12, 0x34, 0x56, 255),
+            theme: HIGH_CONTRAST,
+            pattern: Pattern::Stripes,
+            clock: Some(TimeControl::new(5, Bonus::Delay(3))),
+            animation: Animation::Off,
+            volume: 35,
+            muted: true,
+            starter: Starter::LoserFirst,
+            swap: true,
+            match_order: Order::LoserStarts,
+            seed: Some(42),
+            ..Settings::default()
+        };
+
+        assert_eq!(Settings::decode(&settings.encode()), settings);
+        assert_eq!(
+            Settings::decode(&Settings::default().encode()),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn version_1_colours_move_to_the_custom_theme() {
+        let text = "red_color = \"#123456\"\nsound = false\n";
+        let settings = Settings::decode(text);
+        assert_eq!(settings.theme.name, CUSTOM.name);
+        assert_eq!(settings.red_color, Color::from_bytes(0x12, 0x34, 0x56, 255));
+        assert_eq!(settings.style().theme.red, settings.red_color);
+        // and on through the next step
+        assert!(settings.muted);
+
+        let unchanged = Settings::decode("version = 1\nred_color = \"#ff0000\"\n");
+        assert_eq!(unchanged.theme, CLASSIC);
+    }
+
+    #[test]
+    fn version_2_sound_becomes_muting() {
+        let on = Settings::decode("version = 2\ntheme = \"colour blind\"\nsound = true\n");
+        assert!(!on.muted);
+        assert_eq!(on.theme.name, "colour blind");
+        assert!(Settings::decode("version = 2\nsound = false\n").muted);
+        // steps already taken are not run again
+        let custom = "version = 2\nred_color = \"#123456\"\ntheme = \"classic\"\n";
+        assert_eq!(Settings::decode(custom).theme, CLASSIC);
+    }
+
+    #[test]
+    fn current_files_skip_the_migrations() {
+        let text = format!("version = {}\nsound = false\n", VERSION);
+        assert!(!Settings::decode(&text).muted);
+    }
+
+    #[test]
+    fn broken_and_unknown_values_fall_back() {
+        let text = "\
+# a comment
+[table]
+volume = 250
+muted = maybe
+animation = \"warp\"
+seed = 7 # fixed
+unknown = 1
+yellow_name = \"\"
+";
+        let settings = Settings::decode(text);
+        assert_eq!(settings.volume, 100);
+        assert!(!settings.muted);
+        assert_eq!(settings.animation, Animation::Normal);
+        assert_eq!(settings.seed, Some(7));
+        assert_eq!(settings.yellow_name, "yellow");
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        for text in ["", "plain", "a \"b\" c", "back\\slash", "new\nline\ttab"] {
+            let quoted = quote(text);
+            assert_eq!(unquote(&quoted[1..]), text);
+        }
+        assert_eq!(parse("name = \"a\" # after")["name"], "a");
+    }
+}
@@ -0,0 +1,204 @@
+// Sound effects. The clips are built into the binary like the font, and the
+// game only says what happened: effects are queued during the frame and
+// played at its end with the volume from the settings.
+// Browsers keep audio locked until the page gets a click, tap or key, so on
+// the web nothing is played before that and effects from before are dropped.
+
+use notan::prelude::*;
+
+use crate::board::{Outcome, Player, MAX_ROWS, ROWS};
+use crate::clock::LOW_TIME;
+use crate::settings::Settings;
+use crate::State;
+
+const DROP: &[u8] = include_bytes!("assets/drop.wav");
+const INVALID: &[u8] = include_bytes!("assets/invalid.wav");
+const WIN: &[u8] = include_bytes!("assets/win.wav");
+const DRAW: &[u8] = include_bytes!("assets/draw.wav");
+const WARNING: &[u8] = include_bytes!("assets/warning.wav");
+
+// how much higher the drop sounds for each row the disc falls less
+const PITCH_STEP: f32 = 0.08;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Effect {
+    // a disc landed after falling this many rows
+    Drop(usize),
+    Invalid,
+    Win,
+    Draw,
+    Warning,
+}
+
+struct Clips {
+    // one per fall height, a short fall is a higher knock
+    drops: Vec<AudioSource>,
+    invalid: AudioSource,
+    win: AudioSource,
+    draw: AudioSource,
+    warning: AudioSource,
+}
+
+impl Clips {
+    fn new(audio: &mut Audio) -> Result<Clips, String> {
+        // the longest fall on the classic board sounds as recorded, falls on
+        // taller boards go lower still
+        let drops = (1..=MAX_ROWS)
+            .map(|rows| {
+                let pitch = 1.0 + (ROWS as f32 - rows as f32) * PITCH_STEP;
+                audio.create_source(&with_pitch(DROP, pitch))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Clips {
+            drops,
+            invalid: audio.create_source(INVALID)?,
+            win: audio.create_source(WIN)?,
+            draw: audio.create_source(DRAW)?,
+            warning: audio.create_source(WARNING)?,
+        })
+    }
+
+    fn get(&self, effect: Effect) -> &AudioSource {
+        match effect {
+            Effect::Drop(rows) => &self.drops[rows.clamp(1, MAX_ROWS) - 1],
+            Effect::Invalid => &self.invalid,
+            Effect::Win => &self.win,
+            Effect::Draw => &self.draw,
+            Effect::Warning => &self.warning,
+        }
+    }
+}
+
+pub struct Sounds {
+    // none if the clips could not be loaded, the game is silent then
+    clips: Option<Clips>,
+    queued: Vec<Effect>,
+    // the running clock as of the last frame, to hear it run low
+    clock: Option<(Player, f32)>,
+    over: bool,
+    #[cfg(target_arch = "wasm32")]
+    unlocked: bool,
+}
+
+impl Sounds {
+    pub fn new(audio: &mut Audio) -> Self {
+        let clips = Clips::new(audio)
+            .map_err(|e| eprintln!("could not load the sounds: {}", e))
+            .ok();
+
+        Sounds {
+            clips,
+            queued: vec![],
+            clock: None,
+            over: false,
+            #[cfg(target_arch = "wasm32")]
+            unlocked: false,
+        }
+    }
+
+    pub fn queue(&mut self, effect: Effect) {
+        self.queued.push(effect);
+    }
+
+    // the same events the browser waits for before it starts the audio
+    #[cfg(target_arch = "wasm32")]
+    pub fn event(&mut self, event: &Event) {
+        if matches!(
+            event,
+            Event::KeyDown { .. }
+                | Event::KeyUp { .. }
+                | Event::MouseDown { .. }
+                | Event::MouseUp { .. }
+                | Event::TouchEnd { .. }
+        ) {
+            self.unlocked = true;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn event(&mut self, _event: &Event) {}
+
+    #[cfg(target_arch = "wasm32")]
+    fn unlocked(&self) -> bool {
+        self.unlocked
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn unlocked(&self) -> bool {
+        true
+    }
+
+    fn play(&mut self, audio: &mut Audio, settings: &Settings) {
+        let queued = std::mem::take(&mut self.queued);
+        let Some(clips) = &self.clips else {
+            return;
+        };
+        if settings.muted || settings.volume == 0 || !self.unlocked() {
+            return;
+        }
+
+        for effect in queued {
+            // the handle is only needed to stop or change the sound later
+            let _ = audio.play_sound(clips.get(effect), gain(settings.volume), false);
+        }
+    }
+}
+
+// picks up what happened to the game this frame and plays everything queued
+pub fn update(app: &mut App, state: &mut State) {
+    let sounds = &mut state.sounds;
+    let game = &mut state.game;
+
+    if let Some(rows) = game.landed.take() {
+        sounds.queue(Effect::Drop(rows));
+    }
+
+    let clock = game
+        .clock
+        .filter(|_| game.in_progress())
+        .map(|clock| (game.turn, clock.left(game.turn)));
+    if let (Some((before, left_before)), Some((now, left))) = (sounds.clock, clock) {
+        if before == now && left_before >= LOW_TIME && left < LOW_TIME {
+            sounds.queue(Effect::Warning);
+        }
+    }
+    sounds.clock = clock;
+
+    if game.game_over() && !sounds.over {
+        let won = game.outcome.and_then(Outcome::winner).is_some();
+        sounds.queue(if won { Effect::Win } else { Effect::Draw });
+    }
+    sounds.over = game.game_over();
+
+    sounds.play(&mut app.audio, &state.settings);
+}
+
+// notan takes the volume as a linear fade over 100 dB, so a percentage of
+// loudness is turned into that scale
+fn gain(volume: u8) -> f32 {
+    let amplitude = volume.min(100) as f32 / 100.0;
+    1.0 + amplitude.log10() / 5.0
+}
+
+// the same clip played faster, by claiming a higher sample rate in its header
+fn with_pitch(wav: &[u8], pitch: f32) -> Vec<u8> {
+    let mut wav = wav.to_vec();
+    // chunks follow the 12 byte RIFF header, each an id, a size and the data
+    let mut at = 12;
+    while at + 8 <= wav.len() {
+        let size = u32::from_le_bytes([wav[at + 4], wav[at + 5], wav[at + 6], wav[at + 7]]);
+        if &wav[at..at + 4] == b"fmt " && at + 20 <= wav.len() {
+            // the sample rate and the bytes per second, 4 and 8 bytes into the data
+            for field in [at + 12, at + 16] {
+                let bytes = [wav[field], wav[field + 1], wav[field + 2], wav[field + 3]];
+                let value = (u32::from_le_bytes(bytes) as f32 * pitch) as u32;
+                wav[field..field + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            break;
+        }
+        at += 8 + size as usize + size as usize % 2;
+    }
+
+    wav
+}
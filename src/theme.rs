@@ -0,0 +1,271 @@
+// Looks of the board and the discs. A theme is a named palette, the pattern
+// puts a mark inside each disc so the players can be told apart without
+// relying on colour at all: X and O, or stripes running across or down.
+// Green and purple only play in hot seat games of three or four.
+
+use notan::draw::*;
+use notan::math::{vec2, Vec2};
+use notan::prelude::*;
+
+use crate::board::{Cell, Player};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    pub red: Color,
+    pub yellow: Color,
+    pub green: Color,
+    pub purple: Color,
+    pub grid: Color,
+    pub background: Color,
+    // drawn around every disc so light ones stand out
+    pub outline: Option<Color>,
+}
+
+pub const CLASSIC: Theme = Theme {
+    name: "classic",
+    red: Color::RED,
+    yellow: Color::YELLOW,
+    green: Color::GREEN,
+    purple: Color::new(0.6, 0.2, 0.8, 1.0),
+    grid: Color::BLUE,
+    background: Color::new(0.68, 0.84, 0.90, 0.1),
+    outline: None,
+};
+
+// vermillion, sky blue, bluish green and reddish purple from the Okabe-Ito
+// palette, which stay apart for every common kind of colour blindness
+pub const COLOUR_BLIND: Theme = Theme {
+    name: "colour blind",
+    red: Color::new(0.835, 0.369, 0.0, 1.0),
+    yellow: Color::new(0.337, 0.706, 0.914, 1.0),
+    green: Color::new(0.0, 0.62, 0.451, 1.0),
+    purple: Color::new(0.8, 0.475, 0.655, 1.0),
+    grid: Color::new(0.25, 0.25, 0.25, 1.0),
+    background: Color::new(0.95, 0.95, 0.95, 1.0),
+    outline: None,
+};
+
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high contrast",
+    red: Color::BLACK,
+    yellow: Color::WHITE,
+    green: Color::new(0.35, 0.35, 0.35, 1.0),
+    purple: Color::new(0.7, 0.7, 0.7, 1.0),
+    grid: Color::BLACK,
+    background: Color::WHITE,
+    outline: Some(Color::BLACK),
+};
+
+// the disc colours come from the settings
+pub const CUSTOM: Theme = Theme {
+    name: "custom",
+    ..CLASSIC
+};
+
+pub const THEMES: [Theme; 4] = [CLASSIC, COLOUR_BLIND, HIGH_CONTRAST, CUSTOM];
+
+// what the settings menu offers for the custom discs, blue would be lost on
+// the grid
+pub const DISC_COLORS: [(&str, Color); 9] = [
+    ("red", Color::RED),
+    ("orange", Color::ORANGE),
+    ("yellow", Color::YELLOW),
+    ("green", Color::GREEN),
+    ("aqua", Color::AQUA),
+    ("magenta", Color::MAGENTA),
+    ("pink", Color::PINK),
+    ("white", Color::WHITE),
+    ("black", Color::BLACK),
+];
+
+// colours only count as the same down to the bytes the settings keep
+fn disc_color_index(color: Color) -> Option<usize> {
+    DISC_COLORS
+        .iter()
+        .position(|(_, c)| c.rgba_u8() == color.rgba_u8())
+}
+
+pub fn disc_color_name(color: Color) -> Option<&'static str> {
+    disc_color_index(color).map(|i| DISC_COLORS[i].0)
+}
+
+// colours set by hand in the file go back to the first one
+pub fn next_disc_color(color: Color) -> Color {
+    let i = disc_color_index(color);
+    DISC_COLORS[i.map_or(0, |i| (i + 1) % DISC_COLORS.len())].1
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Theme> {
+        THEMES.into_iter().find(|theme| theme.name == name)
+    }
+
+    pub fn next(self) -> Theme {
+        let i = THEMES.iter().position(|theme| theme.name == self.name);
+        THEMES[i.map_or(0, |i| (i + 1) % THEMES.len())]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Pattern {
+    Plain,
+    // X for red, O for yellow, + for green and a square for purple
+    Symbols,
+    // across for red, down for yellow and diagonal for the others
+    Stripes,
+}
+
+impl Pattern {
+    const ALL: [Pattern; 3] = [Pattern::Plain, Pattern::Symbols, Pattern::Stripes];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Plain => "plain",
+            Pattern::Symbols => "X and O",
+            Pattern::Stripes => "stripes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pattern> {
+        Pattern::ALL
+            .into_iter()
+            .find(|pattern| pattern.name() == name)
+    }
+
+    pub fn next(self) -> Pattern {
+        let i = Pattern::ALL.iter().position(|&pattern| pattern == self);
+        Pattern::ALL[i.map_or(0, |i| (i + 1) % Pattern::ALL.len())]
+    }
+}
+
+// the theme with its disc colours settled, and the pattern to draw in them
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Style {
+    pub theme: Theme,
+    pub pattern: Pattern,
+}
+
+impl Style {
+    pub fn color(&self, player: Player) -> Color {
+        match player {
+            Player::Red => self.theme.red,
+            Player::Yellow => self.theme.yellow,
+            Player::Green => self.theme.green,
+            Player::Purple => self.theme.purple,
+        }
+    }
+
+    pub fn draw_cell(&self, draw: &mut Draw, cell: Cell, radius: f32, pos: Vec2) {
+        match cell {
+            Cell::Disc(player) => self.draw_disc(draw, player, radius, pos),
+            Cell::Wall => self.draw_wall(draw, radius, pos),
+        }
+    }
+
+    // a block of bricks in the colour of the grid
+    pub fn draw_wall(&self, draw: &mut Draw, radius: f32, pos: Vec2) {
+        let half = radius * 0.9;
+        let corner = pos - Vec2::splat(half);
+        draw.rect((corner.x, corner.y), (half * 2.0, half * 2.0))
+            .color(self.theme.grid);
+
+        let mortar = contrast(self.theme.grid);
+        for i in 1..3 {
+            let y = corner.y + half * 2.0 * i as f32 / 3.0;
+            draw.line((corner.x, y), (corner.x + half * 2.0, y))
+                .width(radius * 0.08)
+                .color(mortar);
+        }
+        draw.rect((corner.x, corner.y), (half * 2.0, half * 2.0))
+            .stroke_color(self.theme.outline.unwrap_or(mortar))
+            .stroke(radius * 0.08);
+    }
+
+    // someone else's disc in fog of war, it only shows the cell is taken
+    pub fn draw_hidden(&self, draw: &mut Draw, radius: f32, pos: Vec2) {
+        draw.circle(radius)
+            .position(pos.x, pos.y)
+            .fill_color(self.theme.background)
+            .fill();
+        draw.circle(radius)
+            .position(pos.x, pos.y)
+            .stroke_color(self.theme.grid)
+            .stroke(radius * 0.12);
+    }
+
+    pub fn draw_disc(&self, draw: &mut Draw, player: Player, radius: f32, pos: Vec2) {
+        let color = self.color(player);
+        draw.circle(radius)
+            .position(pos.x, pos.y)
+            .fill_color(color)
+            .fill();
+
+        if let Some(outline) = self.theme.outline {
+            draw.circle(radius)
+                .position(pos.x, pos.y)
+                .stroke_color(outline)
+                .stroke(radius * 0.08);
+        }
+
+        let mark = contrast(color);
+        match (self.pattern, player) {
+            (Pattern::Plain, _) => {}
+            (Pattern::Symbols, Player::Red | Player::Green) => {
+                let arm = radius * 0.35;
+                let dirs = match player {
+                    Player::Red => [vec2(1.0, 1.0), vec2(1.0, -1.0)],
+                    _ => [vec2(1.4, 0.0), vec2(0.0, 1.4)],
+                };
+                for dir in dirs {
+                    let (a, b) = (pos - dir * arm, pos + dir * arm);
+                    draw.line((a.x, a.y), (b.x, b.y))
+                        .width(radius * 0.18)
+                        .color(mark);
+                }
+            }
+            (Pattern::Symbols, Player::Yellow) => {
+                draw.circle(radius * 0.45)
+                    .position(pos.x, pos.y)
+                    .stroke_color(mark)
+                    .stroke(radius * 0.16);
+            }
+            (Pattern::Symbols, Player::Purple) => {
+                let half = radius * 0.4;
+                draw.rect((pos.x - half, pos.y - half), (half * 2.0, half * 2.0))
+                    .stroke_color(mark)
+                    .stroke(radius * 0.16);
+            }
+            (Pattern::Stripes, player) => {
+                // the way the stripes run, and the way they are spaced out
+                let along = match player {
+                    Player::Red => vec2(1.0, 0.0),
+                    Player::Yellow => vec2(0.0, 1.0),
+                    Player::Green => vec2(1.0, 1.0).normalize(),
+                    Player::Purple => vec2(1.0, -1.0).normalize(),
+                };
+                let apart = vec2(-along.y, along.x);
+                for i in -2..=2 {
+                    // each stripe is a chord of the disc
+                    let offset = i as f32 * radius * 0.38;
+                    let half = (radius * radius - offset * offset).sqrt() * 0.9;
+                    let (a, b) = (apart * offset - along * half, apart * offset + along * half);
+                    let (a, b) = (pos + a, pos + b);
+                    draw.line((a.x, a.y), (b.x, b.y))
+                        .width(radius * 0.12)
+                        .color(mark);
+                }
+            }
+        }
+    }
+}
+
+// black on light colours and white on dark ones
+pub fn contrast(color: Color) -> Color {
+    let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+    if luminance > 0.5 {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}